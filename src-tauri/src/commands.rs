@@ -12,7 +12,11 @@ use crate::server::download_paths::{
 use crate::server::screenshare::{
     ScreenShareSessionState, ScreenShareSourceType, StartScreenShareRequest,
 };
-use crate::server::{types::ServerInfo, AppState};
+use crate::server::{
+    qr::generate_qr_data_url,
+    types::{BindScope, QrErrorCorrection, ServerInfo, QR_MAX_SIZE, QR_MIN_SIZE},
+    AppState,
+};
 
 const DOWNLOAD_STARTED_MESSAGE: &str = "Download started in background";
 
@@ -29,6 +33,28 @@ pub async fn get_server_info(state: State<'_, Arc<AppState>>) -> Result<ServerIn
     Ok(state.server_info.clone())
 }
 
+/// Re-renders the portal QR code without restarting the server, e.g. larger
+/// for a hi-dpi display. Missing arguments fall back to `settings.qr`.
+/// Empty in localhost mode, which has no portal QR code.
+#[tauri::command]
+pub async fn regenerate_qr_code(
+    size: Option<u32>,
+    error_correction: Option<QrErrorCorrection>,
+    state: State<'_, Arc<AppState>>,
+) -> Result<String, String> {
+    if state.server_info.bind_scope == BindScope::Localhost {
+        return Ok(String::new());
+    }
+    let mut prefs = state.api_state.history.get_settings().await.qr;
+    if let Some(size) = size {
+        prefs.size = size.clamp(QR_MIN_SIZE, QR_MAX_SIZE);
+    }
+    if let Some(level) = error_correction {
+        prefs.error_correction = level;
+    }
+    Ok(generate_qr_data_url(&state.server_info.url, &prefs))
+}
+
 #[tauri::command]
 pub async fn start_download(
     vod_id: String,
@@ -7,7 +7,11 @@ pub mod server;
 pub mod server {
     pub const SERVER_PORT: u16 = 23455;
     pub mod auth;
+    pub mod breaker;
+    pub mod cache;
     pub mod chat;
+    pub mod client_ip;
+    pub mod config;
     pub mod download;
     pub mod download_paths;
     pub mod dto;
@@ -15,6 +19,12 @@ pub mod server {
     pub mod extensions;
     pub mod history;
     pub mod http_utils;
+    pub mod middleware;
+    pub mod network;
+    pub mod opml;
+    pub mod portal;
+    pub mod qr;
+    pub mod routes;
     pub mod screenshare;
     pub mod state;
     pub mod twitch;
@@ -29,7 +39,7 @@ use std::sync::Arc;
 use tauri::{
     menu::{Menu, MenuItem},
     tray::{TrayIconBuilder, TrayIconEvent},
-    Manager,
+    Manager, WebviewWindowBuilder,
 };
 #[cfg(not(test))]
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
@@ -48,6 +58,57 @@ fn init_tracing() {
         .try_init();
 }
 
+/// Data directory when the platform one can't be resolved (sandboxed or
+/// portable installs): `data` next to the executable if it can be created,
+/// otherwise the temp directory, which may not survive a reboot.
+#[cfg(not(test))]
+fn fallback_data_dir() -> std::path::PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent().map(|dir| dir.join("data")))
+        .filter(|dir| std::fs::create_dir_all(dir).is_ok())
+        .unwrap_or_else(|| std::env::temp_dir().join("NoSubVOD"))
+}
+
+/// Closing the main window only hides it; the app keeps serving from the tray.
+#[cfg(not(test))]
+fn hide_on_close(win: &tauri::WebviewWindow) {
+    let win_clone = win.clone();
+    win.on_window_event(move |event| {
+        if let tauri::WindowEvent::CloseRequested { api, .. } = event {
+            api.prevent_close();
+            if let Err(e) = win_clone.hide() {
+                tracing::warn!("Failed to hide the main window: {e}");
+            }
+        }
+    });
+}
+
+/// Brings the main window back from the tray. Some platforms reap a hidden
+/// webview, so a missing window is rebuilt from `tauri.conf.json`.
+#[cfg(not(test))]
+fn show_main_window(app: &tauri::AppHandle) -> tauri::Result<()> {
+    let win = match app.get_webview_window("main") {
+        Some(win) => win,
+        None => {
+            tracing::warn!("Main window is gone; recreating it");
+            let config = app
+                .config()
+                .app
+                .windows
+                .iter()
+                .find(|w| w.label == "main")
+                .cloned()
+                .ok_or(tauri::Error::WindowNotFound)?;
+            let win = WebviewWindowBuilder::from_config(app, &config)?.build()?;
+            hide_on_close(&win);
+            win
+        }
+    };
+    win.show()?;
+    win.set_focus()
+}
+
 #[cfg(not(test))]
 fn init_rustls_crypto_provider() {
     // rustls 0.23 may require explicit provider installation when both
@@ -86,22 +147,26 @@ pub fn run() {
                 .show_menu_on_left_click(false)
                 .on_tray_icon_event(|tray, event| {
                     if let TrayIconEvent::DoubleClick { .. } = event {
-                        let app = tray.app_handle();
-                        if let Some(win) = app.get_webview_window("main") {
-                            let _ = win.show();
-                            let _ = win.set_focus();
+                        if let Err(e) = show_main_window(tray.app_handle()) {
+                            tracing::error!("Tray double-click could not show the app: {e}");
                         }
                     }
                 })
                 .on_menu_event(|app, event| match event.id.as_ref() {
                     "show" => {
-                        if let Some(win) = app.get_webview_window("main") {
-                            let _ = win.show();
-                            let _ = win.set_focus();
+                        if let Err(e) = show_main_window(app) {
+                            tracing::error!("Tray \"Show App\" could not show the app: {e}");
                         }
                     }
                     "quit" => {
-                        app.exit(0);
+                        // Drain the server and save history before exiting.
+                        let app = app.clone();
+                        tauri::async_runtime::spawn(async move {
+                            if let Some(state) = app.try_state::<Arc<AppState>>() {
+                                state.shutdown().await;
+                            }
+                            app.exit(0);
+                        });
                     }
                     _ => {}
                 })
@@ -109,24 +174,25 @@ pub fn run() {
 
             // ── Intercept close → minimize to tray ─────────────────────────
             if let Some(win) = app.get_webview_window("main") {
-                let win_clone = win.clone();
-                win.on_window_event(move |event| {
-                    if let tauri::WindowEvent::CloseRequested { api, .. } = event {
-                        api.prevent_close();
-                        let _ = win_clone.hide();
-                    }
-                });
+                hide_on_close(&win);
             }
 
             // ── Start Axum HTTP server ─────────────────────────────────────
-            let app_data_dir = app
-                .path()
-                .app_data_dir()
-                .map_err(|e| tauri::Error::from(std::io::Error::other(e.to_string())))?;
+            let app_data_dir = app.path().app_data_dir().unwrap_or_else(|e| {
+                let fallback = fallback_data_dir();
+                eprintln!(
+                    "[NoSubVOD] WARNING: could not resolve the app data directory ({e}); storing data in {}",
+                    fallback.display()
+                );
+                fallback
+            });
+
+            // Resolve portal dist directory in release (bundled resources first).
+            let portal_dist = server::portal::resolve_portal_dist(app.handle());
 
             // Initialize state synchronously (history loaded with std::fs)
             let state = Arc::new(
-                AppState::new(app_data_dir)
+                AppState::new(app_data_dir, portal_dist)
                     .map_err(|e| tauri::Error::from(std::io::Error::other(e.to_string())))?,
             );
             app.manage(state.clone());
@@ -140,6 +206,7 @@ pub fn run() {
         })
         .invoke_handler(tauri::generate_handler![
             commands::get_server_info,
+            commands::regenerate_qr_code,
             commands::start_download,
             commands::start_screen_share,
             commands::stop_screen_share,
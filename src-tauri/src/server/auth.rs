@@ -6,7 +6,7 @@ use tokio::sync::RwLock;
 use once_cell::sync::Lazy;
 
 use axum::{
-    extract::{Query, State},
+    extract::State,
     response::{Html, IntoResponse, Response},
     Json,
 };
@@ -16,6 +16,7 @@ use tracing::{info, instrument};
 
 use super::state::ApiState;
 use super::types::SubEntry;
+use super::validation::{ApiJson, ApiQuery};
 
 // ── CONFIGURE YOUR TWITCH APP HERE ─────────────────────────────────────────────
 // 1. Register your app at https://dev.twitch.tv/console/apps
@@ -126,7 +127,8 @@ fn client_not_configured() -> Response {
         Json(serde_json::json!({
             "error": "Twitch OAuth Client credentials not configured. \
                       Crée src-tauri/.env avec TWITCH_CLIENT_ID=ton_id et TWITCH_CLIENT_SECRET=ton_secret \
-                      (voir src-tauri/.env.example et https://dev.twitch.tv/console/apps)"
+                      (voir src-tauri/.env.example et https://dev.twitch.tv/console/apps)",
+            "code": "OAUTH_NOT_CONFIGURED",
         })),
     )
         .into_response()
@@ -197,7 +199,7 @@ use crate::server::error::{AppError, AppResult};
 /// GET /api/auth/twitch/callback  (Twitch redirects here after user approves)
 #[instrument(skip(state, q), fields(state_token = q.state))]
 pub async fn handle_auth_callback(
-    Query(q): Query<CallbackQuery>,
+    ApiQuery(q): ApiQuery<CallbackQuery>,
     State(state): State<ApiState>,
 ) -> Response {
     info!("Received Twitch OAuth callback");
@@ -394,7 +396,7 @@ pub struct ImportFollowsBody {
 /// Body: { save?: bool }  — if save==true, also persists the "auto-import" setting.
 pub async fn handle_auth_import_follows(
     State(state): State<ApiState>,
-    Json(body): Json<ImportFollowsBody>,
+    ApiJson(body): ApiJson<ImportFollowsBody>,
 ) -> AppResult<Response> {
     let (token, user_id) = {
         let settings = state.history.get_settings().await;
@@ -425,7 +427,7 @@ pub struct ImportFollowsSettingBody {
 
 pub async fn handle_auth_set_import_follows(
     State(state): State<ApiState>,
-    Json(body): Json<ImportFollowsSettingBody>,
+    ApiJson(body): ApiJson<ImportFollowsSettingBody>,
 ) -> AppResult<Response> {
     state
         .history
@@ -508,7 +510,7 @@ pub async fn import_followed_channels(
     }
 
     // ── Batch-fetch avatars (up to 100 IDs per request) ─────────────────────
-    let mut total = 0usize;
+    let mut entries: Vec<SubEntry> = Vec::with_capacity(all_channels.len());
 
     for chunk in all_channels.chunks(100) {
         let ids_param = chunk
@@ -544,18 +546,17 @@ pub async fn import_followed_channels(
 
         for (broadcaster_id, login, display_name) in chunk {
             let avatar = user_map.get(broadcaster_id).cloned().unwrap_or_default();
-            let _ = state
-                .history
-                .add_sub(SubEntry {
-                    login: login.clone(),
-                    display_name: display_name.clone(),
-                    profile_image_url: avatar,
-                })
-                .await;
-            total += 1;
+            entries.push(SubEntry {
+                login: login.clone(),
+                display_name: display_name.clone(),
+                profile_image_url: avatar,
+                modified_at: 0,
+            });
         }
     }
 
+    let total = entries.len();
+    let _ = state.history.add_subs_bulk(entries).await;
     total
 }
 
@@ -0,0 +1,105 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// ── GQL circuit breaker ────────────────────────────────────────────────────────
+
+/// Stops hammering Twitch once it starts answering 401 (integrity check) or
+/// 429: after `threshold` throttled replies in a row, calls are refused for
+/// `cooldown`. Any successful reply closes the breaker again.
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    threshold: u32,
+    cooldown: Duration,
+    state: Mutex<BreakerState>,
+}
+
+#[derive(Debug, Default)]
+struct BreakerState {
+    consecutive_throttles: u32,
+    open_until: Option<Instant>,
+}
+
+impl CircuitBreaker {
+    pub const DEFAULT_THRESHOLD: u32 = 3;
+    pub const DEFAULT_COOLDOWN: Duration = Duration::from_secs(30);
+
+    pub fn new(threshold: u32, cooldown: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1),
+            cooldown,
+            state: Mutex::new(BreakerState::default()),
+        }
+    }
+
+    /// `Err(remaining)` while the breaker is open.
+    pub fn check(&self) -> Result<(), Duration> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        match state.open_until {
+            Some(until) => {
+                let now = Instant::now();
+                if now < until {
+                    return Err(until - now);
+                }
+                // Cooldown over: let calls through, one more throttle re-opens.
+                state.open_until = None;
+                state.consecutive_throttles = self.threshold - 1;
+                Ok(())
+            }
+            None => Ok(()),
+        }
+    }
+
+    pub fn record_success(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state = BreakerState::default();
+    }
+
+    pub fn record_throttle(&self) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.consecutive_throttles += 1;
+        if state.consecutive_throttles >= self.threshold {
+            state.open_until = Some(Instant::now() + self.cooldown);
+        }
+    }
+}
+
+impl Default for CircuitBreaker {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_THRESHOLD, Self::DEFAULT_COOLDOWN)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opens_after_repeated_throttles_and_resets_on_success() {
+        let breaker = CircuitBreaker::new(3, Duration::from_secs(60));
+        breaker.record_throttle();
+        breaker.record_throttle();
+        assert!(breaker.check().is_ok());
+
+        breaker.record_throttle();
+        let remaining = breaker.check().unwrap_err();
+        assert!(remaining > Duration::from_secs(55));
+
+        breaker.record_success();
+        assert!(breaker.check().is_ok());
+        breaker.record_throttle();
+        assert!(breaker.check().is_ok());
+    }
+
+    #[test]
+    fn cooldown_expiry_lets_calls_through_but_reopens_on_next_throttle() {
+        let breaker = CircuitBreaker::new(2, Duration::from_millis(20));
+        breaker.record_throttle();
+        breaker.record_throttle();
+        assert!(breaker.check().is_err());
+
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(breaker.check().is_ok());
+        breaker.record_throttle();
+        assert!(breaker.check().is_err());
+    }
+}
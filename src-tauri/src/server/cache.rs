@@ -0,0 +1,107 @@
+use std::time::{Duration, Instant};
+
+use moka::Expiry;
+use rand::Rng;
+
+// ── Jittered cache expiry ──────────────────────────────────────────────────────
+
+/// Per-entry TTL of `base ± spread`, so entries written together (e.g. one
+/// polling round of live statuses) don't all expire in the same instant and
+/// trigger a synchronized burst of GQL requests.
+#[derive(Debug, Clone, Copy)]
+pub struct JitteredTtl {
+    base: Duration,
+    spread: f64,
+}
+
+impl JitteredTtl {
+    pub const DEFAULT_SPREAD: f64 = 0.10;
+
+    pub fn new(base: Duration) -> Self {
+        Self::with_spread(base, Self::DEFAULT_SPREAD)
+    }
+
+    /// Deterministic TTL, equivalent to `time_to_live(base)`.
+    pub fn fixed(base: Duration) -> Self {
+        Self::with_spread(base, 0.0)
+    }
+
+    pub fn with_spread(base: Duration, spread: f64) -> Self {
+        Self {
+            base,
+            spread: spread.clamp(0.0, 1.0),
+        }
+    }
+
+    /// Inclusive `(min, max)` range a sampled TTL falls in.
+    pub fn bounds(&self) -> (Duration, Duration) {
+        (
+            self.base.mul_f64(1.0 - self.spread),
+            self.base.mul_f64(1.0 + self.spread),
+        )
+    }
+
+    pub fn sample(&self) -> Duration {
+        let (min, max) = self.bounds();
+        if min == max {
+            return self.base;
+        }
+        rand::thread_rng().gen_range(min..=max)
+    }
+}
+
+impl<K, V> Expiry<K, V> for JitteredTtl {
+    fn expire_after_create(&self, _key: &K, _value: &V, _created_at: Instant) -> Option<Duration> {
+        Some(self.sample())
+    }
+
+    // Re-inserting a key refreshes it, matching `time_to_live` semantics.
+    fn expire_after_update(
+        &self,
+        _key: &K,
+        _value: &V,
+        _updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        Some(self.sample())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn jittered_ttls_stay_within_ten_percent_band() {
+        let ttl = JitteredTtl::new(Duration::from_secs(25));
+        let (min, max) = ttl.bounds();
+        assert_eq!(min, Duration::from_millis(22_500));
+        assert_eq!(max, Duration::from_millis(27_500));
+
+        let samples: Vec<Duration> = (0..500).map(|_| ttl.sample()).collect();
+        assert!(samples.iter().all(|d| *d >= min && *d <= max));
+        // Spread must actually happen, otherwise there's no stampede protection.
+        assert!(samples.iter().any(|d| *d != samples[0]));
+    }
+
+    #[test]
+    fn fixed_ttl_is_deterministic() {
+        let ttl = JitteredTtl::fixed(Duration::from_secs(20));
+        assert_eq!(
+            ttl.bounds(),
+            (Duration::from_secs(20), Duration::from_secs(20))
+        );
+        assert!((0..50).all(|_| ttl.sample() == Duration::from_secs(20)));
+    }
+
+    #[tokio::test]
+    async fn cache_entries_expire_after_jittered_ttl() {
+        let cache: moka::future::Cache<String, u32> = moka::future::Cache::builder()
+            .expire_after(JitteredTtl::fixed(Duration::from_millis(50)))
+            .build();
+        cache.insert("k".to_string(), 1).await;
+        assert_eq!(cache.get("k").await, Some(1));
+        tokio::time::sleep(Duration::from_millis(120)).await;
+        assert_eq!(cache.get("k").await, None);
+    }
+}
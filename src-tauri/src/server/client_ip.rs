@@ -0,0 +1,199 @@
+use std::net::{IpAddr, SocketAddr};
+
+use axum::http::HeaderMap;
+
+// ── Trusted proxy ranges ───────────────────────────────────────────────────────
+
+/// A CIDR range (`10.0.0.0/8`, `fd00::/8`) or single address whose forwarded
+/// headers are trusted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TrustedProxy {
+    network: IpAddr,
+    prefix_len: u8,
+}
+
+impl TrustedProxy {
+    pub fn parse(raw: &str) -> Option<Self> {
+        let raw = raw.trim();
+        let (addr, prefix) = match raw.split_once('/') {
+            Some((addr, prefix)) => (addr, Some(prefix.parse::<u8>().ok()?)),
+            None => (raw, None),
+        };
+        let network = addr.parse::<IpAddr>().ok()?.to_canonical();
+        let max_len = if network.is_ipv4() { 32 } else { 128 };
+        let prefix_len = prefix.unwrap_or(max_len);
+        if prefix_len > max_len {
+            return None;
+        }
+        Some(Self {
+            network,
+            prefix_len,
+        })
+    }
+
+    /// Parses a comma-separated list, skipping (and logging) invalid entries.
+    pub fn parse_list(raw: &str) -> Vec<Self> {
+        raw.split(',')
+            .map(str::trim)
+            .filter(|s| !s.is_empty())
+            .filter_map(|entry| {
+                let parsed = Self::parse(entry);
+                if parsed.is_none() {
+                    eprintln!("[NoSubVOD] Ignoring invalid trusted proxy range: {entry}");
+                }
+                parsed
+            })
+            .collect()
+    }
+
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        match (self.network, ip.to_canonical()) {
+            (IpAddr::V4(net), IpAddr::V4(ip)) => {
+                let mask = u32::MAX
+                    .checked_shl(32 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u32::from(net) & mask == u32::from(ip) & mask
+            }
+            (IpAddr::V6(net), IpAddr::V6(ip)) => {
+                let mask = u128::MAX
+                    .checked_shl(128 - u32::from(self.prefix_len))
+                    .unwrap_or(0);
+                u128::from(net) & mask == u128::from(ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+impl std::fmt::Display for TrustedProxy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix_len)
+    }
+}
+
+fn is_trusted(ip: IpAddr, trusted: &[TrustedProxy]) -> bool {
+    trusted.iter().any(|range| range.contains(ip))
+}
+
+// ── Client IP extraction ───────────────────────────────────────────────────────
+
+fn parse_forwarded_ip(raw: &str) -> Option<IpAddr> {
+    let raw = raw.trim().trim_matches('"');
+    raw.parse::<IpAddr>()
+        .ok()
+        .or_else(|| raw.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+        .map(|ip| ip.to_canonical())
+}
+
+/// Resolves the real client address.
+///
+/// Forwarded headers are only honored when the socket peer is a trusted proxy;
+/// otherwise anyone could spoof `X-Forwarded-For` to dodge per-IP limits. The
+/// `X-Forwarded-For` chain is walked right to left, skipping trusted hops, so a
+/// client-supplied prefix is ignored too.
+pub fn client_ip(headers: &HeaderMap, peer: IpAddr, trusted: &[TrustedProxy]) -> IpAddr {
+    let peer = peer.to_canonical();
+    if !is_trusted(peer, trusted) {
+        return peer;
+    }
+
+    let forwarded: Vec<IpAddr> = headers
+        .get_all("x-forwarded-for")
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(','))
+        .filter_map(parse_forwarded_ip)
+        .collect();
+
+    if let Some(ip) = forwarded.iter().rev().find(|ip| !is_trusted(**ip, trusted)) {
+        return *ip;
+    }
+    if let Some(first) = forwarded.first() {
+        return *first;
+    }
+
+    headers
+        .get("x-real-ip")
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_forwarded_ip)
+        .unwrap_or(peer)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.append(*name, HeaderValue::from_str(value).unwrap());
+        }
+        map
+    }
+
+    fn ip(raw: &str) -> IpAddr {
+        raw.parse().unwrap()
+    }
+
+    #[test]
+    fn peer_only_when_no_trusted_proxies() {
+        let map = headers(&[("x-forwarded-for", "203.0.113.7")]);
+        assert_eq!(client_ip(&map, ip("192.168.1.20"), &[]), ip("192.168.1.20"));
+    }
+
+    #[test]
+    fn trusted_proxy_forwarded_header_is_honored() {
+        let trusted = TrustedProxy::parse_list("127.0.0.1, 10.0.0.0/8");
+        let map = headers(&[("x-forwarded-for", "203.0.113.7, 10.1.2.3")]);
+        assert_eq!(
+            client_ip(&map, ip("127.0.0.1"), &trusted),
+            ip("203.0.113.7")
+        );
+
+        let map = headers(&[("x-real-ip", "198.51.100.4")]);
+        assert_eq!(
+            client_ip(&map, ip("10.9.9.9"), &trusted),
+            ip("198.51.100.4")
+        );
+    }
+
+    #[test]
+    fn spoofed_forwarded_header_is_ignored() {
+        let trusted = TrustedProxy::parse_list("10.0.0.0/8");
+
+        // Untrusted peer: header is ignored entirely.
+        let map = headers(&[("x-forwarded-for", "1.2.3.4")]);
+        assert_eq!(
+            client_ip(&map, ip("192.168.1.50"), &trusted),
+            ip("192.168.1.50")
+        );
+
+        // Trusted peer, but the client prepended a fake hop: the right-most
+        // untrusted address (the one the proxy saw) wins.
+        let map = headers(&[("x-forwarded-for", "1.2.3.4, 198.51.100.9")]);
+        assert_eq!(
+            client_ip(&map, ip("10.0.0.2"), &trusted),
+            ip("198.51.100.9")
+        );
+    }
+
+    #[test]
+    fn trusted_proxy_parsing_and_matching() {
+        let v4 = TrustedProxy::parse("192.168.0.0/16").unwrap();
+        assert!(v4.contains(ip("192.168.44.1")));
+        assert!(!v4.contains(ip("192.169.0.1")));
+        assert!(v4.contains(ip("::ffff:192.168.1.1")));
+
+        let v6 = TrustedProxy::parse("fd00::/8").unwrap();
+        assert!(v6.contains(ip("fd12:3456::1")));
+        assert!(!v6.contains(ip("fe80::1")));
+
+        assert!(TrustedProxy::parse("0.0.0.0/0")
+            .unwrap()
+            .contains(ip("8.8.8.8")));
+        assert!(TrustedProxy::parse("10.0.0.0/33").is_none());
+        assert!(TrustedProxy::parse("not-an-ip").is_none());
+        assert_eq!(TrustedProxy::parse_list("10.0.0.1, bogus, ").len(), 1);
+    }
+}
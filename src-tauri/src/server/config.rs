@@ -0,0 +1,261 @@
+use sha2::{Digest, Sha256};
+
+use super::client_ip::TrustedProxy;
+use super::twitch::TrendingOptions;
+
+// ── Runtime configuration ──────────────────────────────────────────────────────
+
+/// Policy for portal pages: the bundle's own assets, plus the Twitch image
+/// CDNs thumbnails and avatars are loaded from. Playback goes through the
+/// `/api` proxy (`blob:` for hls.js), so no media hosts need listing.
+pub const DEFAULT_PORTAL_CSP: &str = "default-src 'self'; \
+    script-src 'self'; \
+    style-src 'self' 'unsafe-inline'; \
+    img-src 'self' data: blob: https://*.jtvnw.net https://*.cloudfront.net; \
+    media-src 'self' blob:; \
+    worker-src 'self' blob:; \
+    connect-src 'self' ws: wss:; \
+    frame-src 'self'; \
+    object-src 'none'; \
+    base-uri 'self'; \
+    frame-ancestors 'none'";
+
+/// Shared portal + API password. Only the SHA-256 of the password is kept,
+/// so it can live in a service file or tunnel config without the plaintext.
+#[derive(Clone)]
+pub struct BasicAuthConfig {
+    pub username: String,
+    password_sha256: [u8; 32],
+}
+
+impl std::fmt::Debug for BasicAuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BasicAuthConfig")
+            .field("username", &self.username)
+            .finish_non_exhaustive()
+    }
+}
+
+impl BasicAuthConfig {
+    /// `password_sha256` is the hex digest, e.g. from `sha256sum`.
+    pub fn from_hash(username: &str, password_sha256: &str) -> Option<Self> {
+        let username = username.trim();
+        let hex = password_sha256.trim();
+        if username.is_empty() || username.contains(':') || hex.len() != 64 {
+            return None;
+        }
+        let mut digest = [0u8; 32];
+        for (i, byte) in digest.iter_mut().enumerate() {
+            *byte = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+        }
+        Some(Self {
+            username: username.to_string(),
+            password_sha256: digest,
+        })
+    }
+
+    pub fn verify(&self, username: &str, password: &str) -> bool {
+        let digest: [u8; 32] = Sha256::digest(password.as_bytes()).into();
+        // Compare every byte so timing doesn't reveal how much matched.
+        let hash_ok = digest
+            .iter()
+            .zip(self.password_sha256.iter())
+            .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+            == 0;
+        hash_ok && username == self.username
+    }
+}
+
+/// Server options read once at startup from `NOSUBVOD_*` environment variables.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    /// Reverse proxies allowed to set `X-Forwarded-For` / `X-Real-IP`
+    /// (`NOSUBVOD_TRUSTED_PROXIES`, comma-separated CIDRs). Empty by default,
+    /// meaning the socket peer address is always used.
+    pub trusted_proxies: Vec<TrustedProxy>,
+    /// `Content-Security-Policy` sent with portal pages
+    /// (`NOSUBVOD_PORTAL_CSP`). `off` drops the header entirely, for users
+    /// who embed custom players the default policy would block.
+    pub portal_csp: Option<String>,
+    /// Optional HTTP Basic Auth in front of everything but `/api/health`
+    /// (`NOSUBVOD_BASIC_AUTH_USER` + `NOSUBVOD_BASIC_AUTH_SHA256`).
+    pub basic_auth: Option<BasicAuthConfig>,
+    /// Bind dual-stack on `[::]` and advertise IPv6 addresses
+    /// (`NOSUBVOD_IPV6=1`). Off by default: most home networks are IPv4-only.
+    pub ipv6: bool,
+    /// Extra hosts the stream proxy may fetch from, on top of the Twitch
+    /// CDNs (`NOSUBVOD_EXTRA_PROXY_HOSTS`, comma-separated; `.example.com`
+    /// also allows subdomains). Empty by default.
+    pub extra_proxy_hosts: Vec<String>,
+    /// Keep history, watchlist, subs and settings in memory only
+    /// (`NOSUBVOD_EPHEMERAL=1`): nothing is read from or written to disk.
+    pub ephemeral: bool,
+    /// How many games, subs and channels a trends build pulls VODs for, and
+    /// how many of those fetches run at once (`NOSUBVOD_TRENDS_TOP_GAMES`,
+    /// `NOSUBVOD_TRENDS_SUB_CHANNELS`, `NOSUBVOD_TRENDS_TOP_CHANNELS`,
+    /// `NOSUBVOD_TRENDS_CONCURRENCY`).
+    pub trending: TrendingOptions,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            trusted_proxies: Vec::new(),
+            portal_csp: Some(DEFAULT_PORTAL_CSP.to_string()),
+            basic_auth: None,
+            ipv6: false,
+            extra_proxy_hosts: Vec::new(),
+            ephemeral: false,
+            trending: TrendingOptions::default(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Effective values for `GET /api/config`. The Basic Auth digest is never
+    /// included, only whether it is on and for which user.
+    pub fn public_view(&self) -> serde_json::Value {
+        serde_json::json!({
+            "trustedProxies": self
+                .trusted_proxies
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            "portalCsp": self.portal_csp,
+            "basicAuth": {
+                "enabled": self.basic_auth.is_some(),
+                "username": self.basic_auth.as_ref().map(|auth| auth.username.as_str()),
+            },
+            "ipv6": self.ipv6,
+            "extraProxyHosts": self.extra_proxy_hosts,
+            "ephemeral": self.ephemeral,
+            "trending": {
+                "topGames": self.trending.top_games,
+                "subChannels": self.trending.sub_channels,
+                "topChannels": self.trending.top_channels,
+                "maxConcurrentFetches": self.trending.max_concurrent_fetches,
+            },
+        })
+    }
+
+    pub fn from_env() -> Self {
+        Self::from_lookup(|key| std::env::var(key).ok())
+    }
+
+    pub fn from_lookup(lookup: impl Fn(&str) -> Option<String>) -> Self {
+        let defaults = Self::default();
+
+        let trusted_proxies = lookup("NOSUBVOD_TRUSTED_PROXIES")
+            .map(|raw| TrustedProxy::parse_list(&raw))
+            .unwrap_or(defaults.trusted_proxies);
+
+        let portal_csp = match lookup("NOSUBVOD_PORTAL_CSP").map(|raw| raw.trim().to_string()) {
+            Some(raw) if raw.eq_ignore_ascii_case("off") => None,
+            Some(raw) if !raw.is_empty() => Some(raw),
+            _ => defaults.portal_csp,
+        };
+
+        let basic_auth = match (
+            lookup("NOSUBVOD_BASIC_AUTH_USER"),
+            lookup("NOSUBVOD_BASIC_AUTH_SHA256"),
+        ) {
+            (Some(user), Some(hash)) => {
+                let parsed = BasicAuthConfig::from_hash(&user, &hash);
+                if parsed.is_none() {
+                    eprintln!(
+                        "[NoSubVOD] Ignoring invalid basic auth config (expected a username without ':' and a 64-char SHA-256 hex digest)"
+                    );
+                }
+                parsed
+            }
+            _ => defaults.basic_auth,
+        };
+
+        let ipv6 = lookup("NOSUBVOD_IPV6")
+            .map(|raw| is_truthy(&raw))
+            .unwrap_or(defaults.ipv6);
+
+        let ephemeral = lookup("NOSUBVOD_EPHEMERAL")
+            .map(|raw| is_truthy(&raw))
+            .unwrap_or(defaults.ephemeral);
+
+        let extra_proxy_hosts = lookup("NOSUBVOD_EXTRA_PROXY_HOSTS")
+            .map(|raw| {
+                raw.split(',')
+                    .map(|host| host.trim().to_ascii_lowercase())
+                    .filter(|host| !host.is_empty() && host != ".")
+                    .collect()
+            })
+            .unwrap_or(defaults.extra_proxy_hosts);
+
+        let count = |key: &str, default: usize, max: usize| {
+            lookup(key).map_or(default, |raw| match raw.trim().parse::<usize>() {
+                Ok(value) if value <= max => value,
+                _ => {
+                    eprintln!("[NoSubVOD] Ignoring invalid {key} (expected 0 to {max})");
+                    default
+                }
+            })
+        };
+        let trending = TrendingOptions {
+            top_games: count("NOSUBVOD_TRENDS_TOP_GAMES", defaults.trending.top_games, 20),
+            sub_channels: count(
+                "NOSUBVOD_TRENDS_SUB_CHANNELS",
+                defaults.trending.sub_channels,
+                100,
+            ),
+            top_channels: count(
+                "NOSUBVOD_TRENDS_TOP_CHANNELS",
+                defaults.trending.top_channels,
+                20,
+            ),
+            max_concurrent_fetches: count(
+                "NOSUBVOD_TRENDS_CONCURRENCY",
+                defaults.trending.max_concurrent_fetches,
+                32,
+            )
+            .max(1),
+        };
+
+        Self {
+            trusted_proxies,
+            portal_csp,
+            basic_auth,
+            ipv6,
+            extra_proxy_hosts,
+            ephemeral,
+            trending,
+        }
+    }
+}
+
+fn is_truthy(raw: &str) -> bool {
+    matches!(
+        raw.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "on" | "yes"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trends_fan_out_is_read_from_env_within_bounds() {
+        let config = ServerConfig::from_lookup(|key| match key {
+            "NOSUBVOD_TRENDS_TOP_GAMES" => Some("8".to_string()),
+            "NOSUBVOD_TRENDS_SUB_CHANNELS" => Some(" 0 ".to_string()),
+            "NOSUBVOD_TRENDS_TOP_CHANNELS" => Some("999".to_string()),
+            "NOSUBVOD_TRENDS_CONCURRENCY" => Some("0".to_string()),
+            _ => None,
+        });
+        let defaults = TrendingOptions::default();
+        assert_eq!(config.trending.top_games, 8);
+        assert_eq!(config.trending.sub_channels, 0);
+        // Out of range falls back to the default.
+        assert_eq!(config.trending.top_channels, defaults.top_channels);
+        // At least one fetch must be allowed to run.
+        assert_eq!(config.trending.max_concurrent_fetches, 1);
+        assert_eq!(config.public_view()["trending"]["topGames"], 8);
+    }
+}
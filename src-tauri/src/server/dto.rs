@@ -1,6 +1,10 @@
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use super::error::{AppError, AppResult};
+use super::opml::logins_from_opml;
+use super::types::{BindScope, QrPreferences, SubEntry, TrendingPreferences};
+
 // ── Query parameter structs ───────────────────────────────────────────────────
 
 #[derive(Deserialize)]
@@ -9,9 +13,31 @@ pub struct ChatQuery {
     pub keyword: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct ChatRangeQuery {
+    pub start: Option<f64>,
+    pub end: Option<f64>,
+}
+
+#[derive(Deserialize)]
+pub struct QrQuery {
+    /// `png` (default) or `svg`.
+    pub format: Option<String>,
+    /// Minimum width in pixels; defaults to `settings.qr.size`.
+    pub size: Option<u32>,
+}
+
+#[derive(Deserialize)]
+pub struct SubsExportQuery {
+    /// `json` (default) or `opml`.
+    pub format: Option<String>,
+}
+
 #[derive(Deserialize)]
 pub struct SearchQuery {
     pub q: Option<String>,
+    pub limit: Option<String>,
+    pub cursor: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -25,6 +51,13 @@ pub struct LiveQuery {
     pub limit: Option<String>,
     pub cursor: Option<String>,
     pub after: Option<String>,
+    #[serde(rename = "excludeReruns")]
+    pub exclude_reruns: Option<String>,
+    /// `viewers_desc`, `viewers_asc` or `recent`; Twitch's order when absent.
+    pub sort: Option<String>,
+    /// Comma-separated broadcaster languages (`fr,en`). Falls back to the
+    /// `preferredLanguage` setting when absent; an empty value disables it.
+    pub lang: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -38,6 +71,11 @@ pub struct PagedQuery {
     pub limit: Option<usize>,
 }
 
+#[derive(Deserialize)]
+pub struct SyncChangesQuery {
+    pub since: Option<u64>,
+}
+
 #[derive(Deserialize)]
 pub struct HistoryListQuery {
     pub limit: Option<String>,
@@ -52,11 +90,33 @@ pub struct SearchCategoryQuery {
     pub limit: Option<String>,
 }
 
+/// Thumbnail size for VOD listings, checked by `parse_thumb_size`.
+#[derive(Deserialize)]
+pub struct ThumbQuery {
+    #[serde(rename = "thumbWidth")]
+    pub thumb_width: Option<u32>,
+    #[serde(rename = "thumbHeight")]
+    pub thumb_height: Option<u32>,
+}
+
 #[derive(Deserialize)]
 pub struct LiveCategoryQuery {
+    pub id: Option<String>,
     pub name: Option<String>,
     pub cursor: Option<String>,
     pub limit: Option<String>,
+    /// Backfill a sparse first page from related categories.
+    #[serde(default)]
+    pub expand: bool,
+}
+
+#[derive(Deserialize)]
+pub struct TrendsQuery {
+    pub offset: Option<String>,
+    pub limit: Option<String>,
+    /// Overrides `settings.trending.minVodLengthSeconds` for this request.
+    #[serde(rename = "minLength")]
+    pub min_length: Option<String>,
 }
 
 #[derive(Deserialize)]
@@ -96,6 +156,31 @@ pub struct SettingsPatch {
     pub auto_update: Option<bool>,
     #[serde(rename = "enabledExtensions")]
     pub enabled_extensions: Option<Vec<String>>,
+    /// Replaces the whole `trending` block when present.
+    pub trending: Option<TrendingPreferences>,
+    /// Replaces the whole `qr` block when present.
+    pub qr: Option<QrPreferences>,
+    /// `null` or an empty string signs out; leaving the key out keeps the
+    /// current token.
+    #[serde(rename = "oauthToken", default, deserialize_with = "present_or_null")]
+    pub oauth_token: Option<Option<String>>,
+    #[serde(rename = "preferredLanguage")]
+    pub preferred_language: Option<Option<String>>,
+    /// Applies on the next launch.
+    #[serde(rename = "bindScope")]
+    pub bind_scope: Option<BindScope>,
+    #[serde(rename = "excludeReruns")]
+    pub exclude_reruns: Option<bool>,
+}
+
+/// Keeps an explicit `null` apart from a missing key: plain serde reads both
+/// as the outer `None`, so with `#[serde(default)]` only a missing key does.
+fn present_or_null<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
 #[derive(Deserialize)]
@@ -106,6 +191,24 @@ pub struct HistoryBody {
     pub duration: Option<f64>,
 }
 
+/// Body of `POST`/`DELETE /api/blocklist`: a channel login, a category
+/// name, or both.
+#[derive(Deserialize)]
+pub struct BlocklistBody {
+    pub channel: Option<String>,
+    pub game: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct VodBatchBody {
+    pub ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ResolveUrlBody {
+    pub url: String,
+}
+
 #[derive(Deserialize)]
 pub struct ChatSendBody {
     pub message: String,
@@ -124,6 +227,60 @@ pub struct DownloadRequest {
     pub duration: Option<f64>,
 }
 
+/// Body of `POST /api/subs/import`: either the JSON array produced by the
+/// export (bare login strings are accepted too), an OPML document or a
+/// newline-delimited list.
+#[derive(Debug, Default)]
+pub struct SubImportBody {
+    /// Entries that already carry display name and avatar.
+    pub entries: Vec<SubEntry>,
+    /// Bare logins still to be enriched through Twitch.
+    pub logins: Vec<String>,
+}
+
+impl SubImportBody {
+    pub fn parse(raw: &str) -> AppResult<Self> {
+        let raw = raw.trim_start_matches('\u{feff}').trim();
+        let mut body = Self::default();
+
+        if raw.starts_with('<') {
+            body.logins = logins_from_opml(raw);
+            return Ok(body);
+        }
+        if !raw.starts_with('[') {
+            body.logins = raw
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty() && !l.starts_with('#'))
+                .map(String::from)
+                .collect();
+            return Ok(body);
+        }
+
+        let items: Vec<Value> = serde_json::from_str(raw)
+            .map_err(|e| AppError::BadRequest(format!("Invalid subs import payload: {e}")))?;
+        for item in items {
+            if let Some(login) = item.as_str() {
+                body.logins.push(login.to_string());
+                continue;
+            }
+            match serde_json::from_value::<SubEntry>(item.clone()) {
+                Ok(entry)
+                    if !entry.display_name.is_empty() && !entry.profile_image_url.is_empty() =>
+                {
+                    body.entries.push(entry)
+                }
+                _ => {
+                    if let Some(login) = item["login"].as_str() {
+                        body.logins.push(login.to_string());
+                    }
+                }
+            }
+        }
+        Ok(body)
+    }
+}
+
 // ── Response structs ───────────────────────────────────────────────────────────
 
 #[derive(Serialize, Clone)]
@@ -1,3 +1,4 @@
+use axum::extract::rejection::{JsonRejection, QueryRejection};
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use serde_json::json;
@@ -17,6 +18,16 @@ pub enum AppError {
     #[error("Twitch API error: {0}")]
     TwitchApi(String),
 
+    /// Twitch answered 401 (integrity check) or 429.
+    #[error("Twitch is rate-limiting requests (HTTP {0})")]
+    TwitchThrottled(u16),
+
+    #[error("Service unavailable: {message}")]
+    ServiceUnavailable {
+        message: String,
+        retry_after_secs: u64,
+    },
+
     #[error("Internal error: {0}")]
     Internal(String),
 
@@ -29,33 +40,254 @@ pub enum AppError {
     #[error("Bad Request: {0}")]
     BadRequest(String),
 
+    /// Request body over the route's size limit.
+    #[error("Payload too large: {0}")]
+    PayloadTooLarge(String),
+
+    /// The resource existed but is over, e.g. a live stream that ended.
+    #[error("Gone: {0}")]
+    Gone(String),
+
     #[error("Poisoned lock")]
     PoisonedLock,
+
+    /// Any of the above with a more specific machine-readable code than its
+    /// variant's, e.g. `USER_NOT_FOUND`. Built with [`AppError::with_code`].
+    #[error("{source}")]
+    Coded {
+        code: &'static str,
+        source: Box<AppError>,
+    },
 }
 
 pub type AppResult<T> = Result<T, AppError>;
 
+impl AppError {
+    /// Tags the error with `code`, replacing any earlier tag.
+    pub fn with_code(self, code: &'static str) -> Self {
+        AppError::Coded {
+            code,
+            source: Box::new(self.into_inner()),
+        }
+    }
+
+    /// The `code` field of the JSON error body.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Coded { code, .. } => *code,
+            AppError::Io(_)
+            | AppError::Json(_)
+            | AppError::Internal(_)
+            | AppError::PoisonedLock => "INTERNAL",
+            AppError::Http(_) | AppError::TwitchApi(_) => "TWITCH_UPSTREAM",
+            AppError::TwitchThrottled(_) => "TWITCH_THROTTLED",
+            AppError::ServiceUnavailable { .. } => "SERVICE_UNAVAILABLE",
+            AppError::Unauthorized(_) => "UNAUTHORIZED",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::BadRequest(_) => "INVALID_INPUT",
+            AppError::PayloadTooLarge(_) => "PAYLOAD_TOO_LARGE",
+            AppError::Gone(_) => "GONE",
+        }
+    }
+
+    /// Whether this is a [`AppError::NotFound`], tagged or not.
+    pub fn is_not_found(&self) -> bool {
+        match self {
+            AppError::Coded { source, .. } => source.is_not_found(),
+            e => matches!(e, AppError::NotFound(_)),
+        }
+    }
+
+    fn into_inner(self) -> Self {
+        match self {
+            AppError::Coded { source, .. } => source.into_inner(),
+            e => e,
+        }
+    }
+}
+
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let (status, message) = match self {
+        let code = self.code();
+        let error = self.into_inner();
+        let retry_after = match &error {
+            AppError::ServiceUnavailable {
+                retry_after_secs, ..
+            } => Some(*retry_after_secs),
+            _ => None,
+        };
+        let (status, message) = match error {
             AppError::Io(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
             AppError::Json(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
             AppError::Http(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
             AppError::TwitchApi(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
+            e @ AppError::TwitchThrottled(_) => (StatusCode::SERVICE_UNAVAILABLE, e.to_string()),
+            AppError::ServiceUnavailable { message, .. } => {
+                (StatusCode::SERVICE_UNAVAILABLE, message)
+            }
             AppError::Internal(msg) => (StatusCode::INTERNAL_SERVER_ERROR, msg),
             AppError::Unauthorized(msg) => (StatusCode::UNAUTHORIZED, msg),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg),
             AppError::BadRequest(msg) => (StatusCode::BAD_REQUEST, msg),
+            AppError::PayloadTooLarge(msg) => (StatusCode::PAYLOAD_TOO_LARGE, msg),
+            AppError::Gone(msg) => (StatusCode::GONE, msg),
             AppError::PoisonedLock => (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 "Internal locking error".to_string(),
             ),
+            AppError::Coded { .. } => unreachable!("into_inner strips code tags"),
         };
 
         let body = json!({
             "error": message,
+            "code": code,
         });
 
-        (status, axum::Json(body)).into_response()
+        let mut response = (status, axum::Json(body)).into_response();
+        if let Some(secs) = retry_after {
+            response.headers_mut().insert(
+                axum::http::header::RETRY_AFTER,
+                axum::http::HeaderValue::from(secs),
+            );
+        }
+        response
+    }
+}
+
+/// Malformed, mistyped or oversized JSON bodies, via [`ApiJson`].
+///
+/// [`ApiJson`]: super::validation::ApiJson
+impl From<JsonRejection> for AppError {
+    fn from(rejection: JsonRejection) -> Self {
+        if rejection.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            AppError::PayloadTooLarge(rejection.body_text())
+        } else {
+            AppError::BadRequest(rejection.body_text())
+        }
+    }
+}
+
+/// Query strings that don't deserialize, via [`ApiQuery`].
+///
+/// [`ApiQuery`]: super::validation::ApiQuery
+impl From<QueryRejection> for AppError {
+    fn from(rejection: QueryRejection) -> Self {
+        AppError::BadRequest(rejection.body_text())
+    }
+}
+
+/// Fallback for unmatched `/api/*` paths, so typos get a JSON 404 instead of
+/// falling through to the portal's HTML.
+pub async fn handle_api_not_found() -> Response {
+    (
+        StatusCode::NOT_FOUND,
+        axum::Json(json!({
+            "error": "unknown endpoint",
+            "code": "NOT_FOUND",
+        })),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn unknown_api_paths_return_json_404_before_portal_fallback() {
+        let dist = tempfile::tempdir().unwrap();
+        std::fs::write(dist.path().join("index.html"), "<!doctype html>portal").unwrap();
+
+        let api = Router::new()
+            .route("/history", get(|| async { "[]" }))
+            .fallback(handle_api_not_found);
+        let app = crate::server::portal::mount_portal_with(
+            Router::new().nest("/api", api),
+            Some(dist.path().to_path_buf()),
+            false,
+        );
+
+        for uri in ["/api/nope", "/api/histor", "/api/dev/missing"] {
+            let response = app
+                .clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::NOT_FOUND, "{uri}");
+            assert_eq!(
+                response.headers()[axum::http::header::CONTENT_TYPE],
+                "application/json",
+                "{uri}"
+            );
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            assert_eq!(body["code"], "NOT_FOUND");
+        }
+
+        // Non-API paths still reach the SPA.
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/library")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn service_unavailable_is_503_with_retry_after() {
+        let response = AppError::ServiceUnavailable {
+            message: "Twitch is rate-limiting us".to_string(),
+            retry_after_secs: 30,
+        }
+        .into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(response.headers()[axum::http::header::RETRY_AFTER], "30");
+
+        let response = AppError::TwitchThrottled(429).into_response();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[tokio::test]
+    async fn error_bodies_carry_a_code() {
+        let body = |error: AppError| async move {
+            let response = error.into_response();
+            let status = response.status();
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+            (status, body)
+        };
+
+        let (status, json) = body(AppError::BadRequest("Invalid username".to_string())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(json["code"], "INVALID_INPUT");
+        assert_eq!(json["error"], "Invalid username");
+
+        let tagged = AppError::NotFound("User not found".to_string())
+            .with_code("VOD_NOT_FOUND")
+            .with_code("USER_NOT_FOUND");
+        assert!(tagged.is_not_found());
+        let (status, json) = body(tagged).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(json["code"], "USER_NOT_FOUND");
+        assert_eq!(json["error"], "User not found");
+
+        let (_, json) = body(AppError::TwitchApi("GQL error".to_string())).await;
+        assert_eq!(json["code"], "TWITCH_UPSTREAM");
+    }
+
+    #[test]
+    fn gone_is_410() {
+        let response = AppError::Gone("stream ended".to_string()).into_response();
+        assert_eq!(response.status(), StatusCode::GONE);
     }
 }
@@ -1,10 +1,10 @@
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, SystemTime, UNIX_EPOCH};
 use tauri::async_runtime;
-use tokio::sync::{Notify, RwLock};
+use tokio::sync::{broadcast, Mutex, Notify, OwnedMutexGuard, RwLock};
 
 use aes_gcm::aead::{Aead, KeyInit};
 use aes_gcm::{Aes256Gcm, Key, Nonce};
@@ -16,8 +16,12 @@ use sha2::{Digest, Sha256};
 
 use super::error::{AppError, AppResult};
 use super::types::{
-    ExperienceSettings, HistoryEntry, PersistedData, SubEntry, TrustedDevice, WatchlistEntry,
+    BindScope, ExperienceSettings, HistoryEntry, PersistedData, QrPreferences, SubEntry,
+    SyncChanges, SyncCollection, SyncMarkers, SyncRemovals, SyncTombstone, TrendingPreferences,
+    TrustedDevice, WatchlistEntry, MAX_BLOCKLIST_ENTRIES, MAX_CHANNEL_SLOTS, MAX_PINNED_GAMES,
+    QR_MAX_SIZE, QR_MIN_SIZE,
 };
+use super::validation::{parse_language_list, parse_vod_id};
 
 // ── Token encryption helpers ───────────────────────────────────────────────────
 // Uses a machine-specific key derived from the data dir path + a salt.
@@ -75,6 +79,48 @@ fn decrypt_token(encoded: &str, key: &[u8]) -> Option<String> {
     String::from_utf8(plaintext).ok()
 }
 
+/// Rewrites history and watchlist ids to the bare digits the routes accept;
+/// older versions stored whatever id the client sent. `v`-prefixed ids are
+/// kept as their digits (the newer entry wins a clash) and ids that can never
+/// be read back or removed are dropped. Returns whether anything changed.
+fn normalize_stored_vod_ids(data: &mut PersistedData) -> bool {
+    let mut changed = false;
+
+    let mut history: HashMap<String, HistoryEntry> = HashMap::with_capacity(data.history.len());
+    for (key, mut entry) in std::mem::take(&mut data.history) {
+        let Ok(id) = parse_vod_id(&key) else {
+            changed = true;
+            continue;
+        };
+        if id != key || entry.vod_id != id {
+            changed = true;
+            entry.vod_id = id.clone();
+        }
+        match history.get(&id) {
+            Some(kept) if kept.updated_at >= entry.updated_at => {}
+            _ => {
+                history.insert(id, entry);
+            }
+        }
+    }
+    data.history = history;
+
+    let before = data.watchlist.len();
+    let mut seen = std::collections::HashSet::new();
+    data.watchlist
+        .retain_mut(|entry| match parse_vod_id(&entry.vod_id) {
+            Ok(id) => {
+                if id != entry.vod_id {
+                    changed = true;
+                    entry.vod_id = id;
+                }
+                seen.insert(entry.vod_id.clone())
+            }
+            Err(_) => false,
+        });
+    changed || data.watchlist.len() != before
+}
+
 // ── HistoryStore – wraps all persisted state ───────────────────────────────────
 
 pub struct HistoryStore {
@@ -84,10 +130,44 @@ pub struct HistoryStore {
     token_key: Vec<u8>,
     /// Whether the data has changed since the last save
     dirty: Arc<AtomicBool>,
+    /// Held from the `dirty` check until the file is written, so saves never
+    /// overlap and `flush` waits for one already under way.
+    save_lock: Arc<Mutex<()>>,
     /// Notifier to wake up the background saver task
     save_notifier: Arc<Notify>,
+    /// Bumped on every change; with `epoch`, backs the ETag of the GET routes.
+    revision: Arc<AtomicU64>,
+    /// Random per process, so revisions never repeat across restarts.
+    epoch: u64,
+    /// In-memory only: nothing is loaded from or saved to `file_path`.
+    ephemeral: bool,
+    /// Change notifications for `/api/events`; sending never waits on readers.
+    events: broadcast::Sender<StoreEvent>,
 }
 
+/// What a write touched, pushed to `/api/events` subscribers so other portal
+/// clients can refetch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StoreEvent {
+    HistoryUpdated,
+    WatchlistUpdated,
+}
+
+impl StoreEvent {
+    pub const ALL: [StoreEvent; 2] = [StoreEvent::HistoryUpdated, StoreEvent::WatchlistUpdated];
+
+    /// SSE event name.
+    pub fn name(self) -> &'static str {
+        match self {
+            StoreEvent::HistoryUpdated => "history-updated",
+            StoreEvent::WatchlistUpdated => "watchlist-updated",
+        }
+    }
+}
+
+/// Events a slow subscriber may fall behind by before it is told to resync.
+const STORE_EVENTS_CAPACITY: usize = 64;
+
 impl HistoryStore {
     /// Load from disk synchronously (file is small – safe to block on startup).
     pub fn load(data_dir: PathBuf) -> AppResult<Self> {
@@ -107,25 +187,64 @@ impl HistoryStore {
         if let Some(ref encrypted) = data.twitch_token {
             data.twitch_token = decrypt_token(encrypted, &token_key);
         }
+        if let Some(ref encrypted) = data.settings.oauth_token {
+            data.settings.oauth_token = decrypt_token(encrypted, &token_key);
+        }
+
+        // Entries saved before change tracking count as modified when added.
+        for entry in data.watchlist.iter_mut().filter(|w| w.modified_at == 0) {
+            entry.modified_at = entry.added_at;
+        }
+        let migrated = normalize_stored_vod_ids(&mut data);
 
         let store = Self {
             data: Arc::new(RwLock::new(data)),
             file_path,
             token_key,
             dirty: Arc::new(AtomicBool::new(false)),
+            save_lock: Arc::new(Mutex::new(())),
             save_notifier: Arc::new(Notify::new()),
+            revision: Arc::new(AtomicU64::new(0)),
+            epoch: rand::random(),
+            ephemeral: false,
+            events: broadcast::channel(STORE_EVENTS_CAPACITY).0,
         };
 
         store.spawn_background_saver();
+        if migrated {
+            store.schedule_save();
+        }
 
         Ok(store)
     }
 
+    /// Starts empty and never touches the disk, whatever `history.json`
+    /// holds; everything is lost when the app exits.
+    pub fn in_memory(data_dir: PathBuf) -> Self {
+        Self {
+            data: Arc::new(RwLock::new(PersistedData::default())),
+            file_path: data_dir.join("history.json"),
+            token_key: derive_key(&data_dir),
+            dirty: Arc::new(AtomicBool::new(false)),
+            save_lock: Arc::new(Mutex::new(())),
+            save_notifier: Arc::new(Notify::new()),
+            revision: Arc::new(AtomicU64::new(0)),
+            epoch: rand::random(),
+            ephemeral: true,
+            events: broadcast::channel(STORE_EVENTS_CAPACITY).0,
+        }
+    }
+
+    pub fn is_ephemeral(&self) -> bool {
+        self.ephemeral
+    }
+
     fn spawn_background_saver(&self) {
         let data = self.data.clone();
         let file_path = self.file_path.clone();
         let token_key = self.token_key.clone();
         let dirty = self.dirty.clone();
+        let save_lock = self.save_lock.clone();
         let notifier = self.save_notifier.clone();
 
         async_runtime::spawn(async move {
@@ -137,8 +256,9 @@ impl HistoryStore {
                 tokio::time::sleep(Duration::from_secs(3)).await;
 
                 // Check if still dirty and save
+                let guard = save_lock.clone().lock_owned().await;
                 if dirty.swap(false, Ordering::SeqCst) {
-                    if let Err(e) = Self::perform_save(&data, &file_path, &token_key).await {
+                    if let Err(e) = Self::perform_save(&data, &file_path, &token_key, guard).await {
                         eprintln!("[history] Failed to background save: {:?}", e);
                         // If save failed, put back the dirty flag so we try again later
                         dirty.store(true, Ordering::SeqCst);
@@ -148,10 +268,13 @@ impl HistoryStore {
         });
     }
 
+    /// Writes a snapshot of `data_lock`. The guard moves into the blocking
+    /// write, so the save lock stays held even if the caller stops waiting.
     async fn perform_save(
         data_lock: &RwLock<PersistedData>,
         file_path: &Path,
         token_key: &[u8],
+        save_guard: OwnedMutexGuard<()>,
     ) -> AppResult<()> {
         let mut disk_data = data_lock.read().await.clone();
 
@@ -163,14 +286,26 @@ impl HistoryStore {
         let token_key_clone = token_key.to_vec();
 
         tokio::task::spawn_blocking(move || {
+            let _save_guard = save_guard;
             // Move encryption inside the blocking task
             if let Some(ref plaintext) = disk_data.twitch_token {
                 disk_data.twitch_token = Some(encrypt_token(plaintext, &token_key_clone)?);
             }
+            if let Some(ref plaintext) = disk_data.settings.oauth_token {
+                disk_data.settings.oauth_token = Some(encrypt_token(plaintext, &token_key_clone)?);
+            }
 
-            let file = std::fs::File::create(file_path_clone)?;
-            let writer = std::io::BufWriter::new(file);
-            serde_json::to_writer_pretty(writer, &disk_data)?;
+            // Write beside the file and rename over it: quitting mid-write
+            // then leaves the previous history.json intact, not a truncated one.
+            let tmp_path = file_path_clone.with_extension("json.tmp");
+            let file = std::fs::File::create(&tmp_path)?;
+            let mut writer = std::io::BufWriter::new(file);
+            serde_json::to_writer_pretty(&mut writer, &disk_data)?;
+            let file = writer
+                .into_inner()
+                .map_err(|e| AppError::Io(e.into_error()))?;
+            file.sync_all()?;
+            std::fs::rename(&tmp_path, &file_path_clone)?;
             Ok::<(), AppError>(())
         })
         .await
@@ -179,7 +314,53 @@ impl HistoryStore {
         Ok(())
     }
 
+    /// Writes pending changes now instead of waiting for the debounced
+    /// background saver; used on quit so the last edits are not lost. Waits
+    /// for a background save that is already writing.
+    pub async fn flush(&self) -> AppResult<()> {
+        if self.ephemeral {
+            return Ok(());
+        }
+        let guard = self.save_lock.clone().lock_owned().await;
+        if !self.dirty.swap(false, Ordering::SeqCst) {
+            return Ok(());
+        }
+        if let Err(e) =
+            Self::perform_save(&self.data, &self.file_path, &self.token_key, guard).await
+        {
+            self.dirty.store(true, Ordering::SeqCst);
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Opaque tag for the current state of history, watchlist, subs and
+    /// settings. Read it before the data it labels: a concurrent write then
+    /// leaves the tag behind the body, never ahead, so a 304 is never stale.
+    pub fn etag(&self) -> String {
+        format!(
+            "\"{}-{}\"",
+            self.epoch,
+            self.revision.load(Ordering::SeqCst)
+        )
+    }
+
+    /// Receives an event after each history or watchlist write.
+    pub fn subscribe(&self) -> broadcast::Receiver<StoreEvent> {
+        self.events.subscribe()
+    }
+
+    fn publish(&self, event: StoreEvent) {
+        // No subscribers is the common case, not an error.
+        let _ = self.events.send(event);
+    }
+
     fn schedule_save(&self) {
+        // Every write method ends here once its lock is released.
+        self.revision.fetch_add(1, Ordering::SeqCst);
+        if self.ephemeral {
+            return;
+        }
         self.dirty.store(true, Ordering::SeqCst);
         self.save_notifier.notify_one();
     }
@@ -225,7 +406,7 @@ impl HistoryStore {
         timecode: f64,
         duration: f64,
     ) -> AppResult<HistoryEntry> {
-        let timecode = timecode.max(0.0);
+        let (timecode, duration) = sanitize_progress(timecode, duration)?;
         let updated_at = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .map_err(|e| AppError::Internal(e.to_string()))?
@@ -244,9 +425,36 @@ impl HistoryStore {
         }
 
         self.schedule_save();
+        self.publish(StoreEvent::HistoryUpdated);
         Ok(entry)
     }
 
+    /// Fills in missing durations from fetched VOD lengths. `updated_at` is
+    /// kept as-is so backfilled entries don't jump to the top of the history.
+    pub async fn backfill_durations(&self, lengths: &HashMap<String, u64>) -> AppResult<usize> {
+        let mut filled = 0usize;
+        {
+            let mut data = self.data.write().await;
+            for entry in data.history.values_mut() {
+                if entry.duration > 0.0 {
+                    continue;
+                }
+                let Some(&length) = lengths.get(&entry.vod_id).filter(|l| **l > 0) else {
+                    continue;
+                };
+                let (timecode, duration) = sanitize_progress(entry.timecode, length as f64)?;
+                entry.timecode = timecode;
+                entry.duration = duration;
+                filled += 1;
+            }
+        }
+        if filled > 0 {
+            self.schedule_save();
+            self.publish(StoreEvent::HistoryUpdated);
+        }
+        Ok(filled)
+    }
+
     // ── Watchlist ────────────────────────────────────────────────────────────
 
     pub async fn get_watchlist(&self) -> Vec<WatchlistEntry> {
@@ -277,23 +485,71 @@ impl HistoryStore {
         (paginated, total)
     }
 
-    pub async fn add_to_watchlist(&self, mut entry: WatchlistEntry) -> AppResult<WatchlistEntry> {
-        let mut should_save = false;
-        {
+    /// Adds `entry` unless the VOD is already listed. The check and the insert
+    /// happen under a single write guard, so concurrent or retried adds cannot
+    /// create duplicates. Returns the stored entry and whether it was new.
+    pub async fn add_to_watchlist(
+        &self,
+        mut entry: WatchlistEntry,
+    ) -> AppResult<(WatchlistEntry, bool)> {
+        let (stored, added) = {
             let mut data = self.data.write().await;
-            if !data.watchlist.iter().any(|w| w.vod_id == entry.vod_id) {
-                entry.added_at = SystemTime::now()
-                    .duration_since(UNIX_EPOCH)
-                    .map_err(|e| AppError::Internal(e.to_string()))?
-                    .as_millis() as u64;
-                data.watchlist.push(entry.clone());
-                should_save = true;
+            match data.watchlist.iter().find(|w| w.vod_id == entry.vod_id) {
+                Some(existing) => (existing.clone(), false),
+                None => {
+                    entry.added_at = now_ms()?;
+                    entry.modified_at = entry.added_at;
+                    clear_tombstone(&mut data, SyncCollection::Watchlist, &entry.vod_id);
+                    data.watchlist.push(entry.clone());
+                    (entry, true)
+                }
             }
-        }
-        if should_save {
+        };
+        if added {
             self.schedule_save();
+            self.publish(StoreEvent::WatchlistUpdated);
         }
-        Ok(entry)
+        Ok((stored, added))
+    }
+
+    /// Moves a watchlist entry into history as fully watched, under one write
+    /// guard. The known history duration wins; otherwise the entry's
+    /// `length_seconds` is used. Returns the remaining watchlist, or `None`
+    /// when the VOD was not on it.
+    pub async fn mark_watchlist_watched(
+        &self,
+        vod_id: &str,
+    ) -> AppResult<Option<Vec<WatchlistEntry>>> {
+        let now = now_ms()?;
+        let watchlist = {
+            let mut data = self.data.write().await;
+            let Some(pos) = data.watchlist.iter().position(|w| w.vod_id == vod_id) else {
+                return Ok(None);
+            };
+            let entry = data.watchlist.remove(pos);
+            record_tombstone(&mut data, SyncCollection::Watchlist, vod_id, now);
+
+            let duration = data
+                .history
+                .get(vod_id)
+                .map(|h| h.duration)
+                .filter(|d| *d > 0.0)
+                .unwrap_or(entry.length_seconds as f64);
+            data.history.insert(
+                vod_id.to_string(),
+                HistoryEntry {
+                    vod_id: vod_id.to_string(),
+                    timecode: duration,
+                    duration,
+                    updated_at: now,
+                },
+            );
+            data.watchlist.clone()
+        };
+        self.schedule_save();
+        self.publish(StoreEvent::WatchlistUpdated);
+        self.publish(StoreEvent::HistoryUpdated);
+        Ok(Some(watchlist))
     }
 
     pub async fn remove_from_watchlist(&self, vod_id: &str) -> AppResult<()> {
@@ -303,11 +559,13 @@ impl HistoryStore {
             let initial_len = data.watchlist.len();
             data.watchlist.retain(|w| w.vod_id != vod_id);
             if data.watchlist.len() != initial_len {
+                record_tombstone(&mut data, SyncCollection::Watchlist, vod_id, now_ms()?);
                 should_save = true;
             }
         }
         if should_save {
             self.schedule_save();
+            self.publish(StoreEvent::WatchlistUpdated);
         }
         Ok(())
     }
@@ -332,7 +590,23 @@ impl HistoryStore {
         launch_at_login: Option<bool>,
         auto_update: Option<bool>,
         enabled_extensions: Option<Vec<String>>,
+        trending: Option<TrendingPreferences>,
+        qr: Option<QrPreferences>,
+        oauth_token: Option<Option<String>>,
+        preferred_language: Option<Option<String>>,
+        bind_scope: Option<BindScope>,
+        exclude_reruns: Option<bool>,
     ) -> AppResult<ExperienceSettings> {
+        let trending = trending.map(sanitize_trending_preferences).transpose()?;
+        let qr = qr.map(validate_qr_preferences).transpose()?;
+        let oauth_token = oauth_token
+            .map(|token| token.map(|t| normalize_oauth_token(&t)).transpose())
+            .transpose()?
+            .map(Option::flatten);
+        let preferred_language = preferred_language
+            .map(|lang| lang.map(|l| normalize_preferred_language(&l)).transpose())
+            .transpose()?
+            .map(Option::flatten);
         {
             let mut data = self.data.write().await;
             if let Some(v) = one_sync {
@@ -368,6 +642,24 @@ impl HistoryStore {
             if let Some(v) = enabled_extensions {
                 data.settings.enabled_extensions = v;
             }
+            if let Some(v) = trending {
+                data.settings.trending = v;
+            }
+            if let Some(v) = qr {
+                data.settings.qr = v;
+            }
+            if let Some(v) = oauth_token {
+                data.settings.oauth_token = v;
+            }
+            if let Some(v) = preferred_language {
+                data.settings.preferred_language = v;
+            }
+            if let Some(v) = bind_scope {
+                data.settings.bind_scope = v;
+            }
+            if let Some(v) = exclude_reruns {
+                data.settings.exclude_reruns = v;
+            }
         }
         self.schedule_save();
         Ok(self.data.read().await.settings.clone())
@@ -379,6 +671,17 @@ impl HistoryStore {
         self.data.read().await.subs.clone()
     }
 
+    pub async fn get_sub(&self, login: &str) -> Option<SubEntry> {
+        let login = login.trim().to_lowercase();
+        self.data
+            .read()
+            .await
+            .subs
+            .iter()
+            .find(|s| s.login == login)
+            .cloned()
+    }
+
     pub async fn get_subs_paged(&self, offset: usize, limit: usize) -> (Vec<SubEntry>, usize) {
         let data = self.data.read().await;
         let total = data.subs.len();
@@ -409,10 +712,12 @@ impl HistoryStore {
         {
             let mut data = self.data.write().await;
             if !data.subs.iter().any(|s| s.login == login) {
+                clear_tombstone(&mut data, SyncCollection::Subs, &login);
                 data.subs.push(SubEntry {
                     login: login.clone(),
                     display_name: entry.display_name.clone(),
                     profile_image_url: entry.profile_image_url.clone(),
+                    modified_at: now_ms()?,
                 });
                 should_save = true;
             }
@@ -423,6 +728,63 @@ impl HistoryStore {
         Ok(entry)
     }
 
+    /// Merges many subs under a single write lock and save.
+    /// Returns how many entries were not already present.
+    pub async fn add_subs_bulk(&self, entries: Vec<SubEntry>) -> AppResult<usize> {
+        let mut added = 0usize;
+        let now = now_ms()?;
+        {
+            let mut data = self.data.write().await;
+            for entry in entries {
+                let login = entry.login.trim().to_lowercase();
+                if login.is_empty() || data.subs.iter().any(|s| s.login == login) {
+                    continue;
+                }
+                clear_tombstone(&mut data, SyncCollection::Subs, &login);
+                data.subs.push(SubEntry {
+                    login,
+                    display_name: entry.display_name,
+                    profile_image_url: entry.profile_image_url,
+                    modified_at: now,
+                });
+                added += 1;
+            }
+        }
+        if added > 0 {
+            self.schedule_save();
+        }
+        Ok(added)
+    }
+
+    /// Overwrites the display name and avatar of stored subs from fresh
+    /// entries, under one write lock and save. Returns how many changed.
+    pub async fn update_sub_metadata(&self, updates: Vec<SubEntry>) -> AppResult<usize> {
+        let mut updated = 0usize;
+        let now = now_ms()?;
+        {
+            let mut data = self.data.write().await;
+            for update in updates {
+                let login = update.login.trim().to_lowercase();
+                let Some(sub) = data.subs.iter_mut().find(|s| s.login == login) else {
+                    continue;
+                };
+                if sub.display_name == update.display_name
+                    && sub.profile_image_url == update.profile_image_url
+                {
+                    continue;
+                }
+                sub.display_name = update.display_name;
+                sub.profile_image_url = update.profile_image_url;
+                sub.modified_at = now;
+                updated += 1;
+            }
+        }
+        if updated > 0 {
+            self.schedule_save();
+        }
+        Ok(updated)
+    }
+
     pub async fn remove_sub(&self, login: &str) -> AppResult<()> {
         let login = login.trim().to_lowercase();
         let mut should_save = false;
@@ -431,6 +793,7 @@ impl HistoryStore {
             let initial_len = data.subs.len();
             data.subs.retain(|s| s.login != login);
             if data.subs.len() != initial_len {
+                record_tombstone(&mut data, SyncCollection::Subs, &login, now_ms()?);
                 should_save = true;
             }
         }
@@ -440,6 +803,95 @@ impl HistoryStore {
         Ok(())
     }
 
+    pub async fn subs_count(&self) -> usize {
+        self.data.read().await.subs.len()
+    }
+
+    /// Removes every sub in one write and one save. Returns how many were removed.
+    pub async fn clear_subs(&self) -> AppResult<usize> {
+        let now = now_ms()?;
+        let removed = {
+            let mut data = self.data.write().await;
+            let removed = std::mem::take(&mut data.subs);
+            for sub in &removed {
+                record_tombstone(&mut data, SyncCollection::Subs, &sub.login, now);
+            }
+            removed.len()
+        };
+        if removed > 0 {
+            self.schedule_save();
+        }
+        Ok(removed)
+    }
+
+    // ── Multi-device sync ────────────────────────────────────────────────────
+
+    /// Everything changed strictly after `since` (ms). `since == 0` returns
+    /// the full collections, including entries that predate change tracking.
+    pub async fn get_sync_changes(&self, since: u64) -> AppResult<SyncChanges> {
+        let server_time = now_ms()?;
+        let data = self.data.read().await;
+        let changed = |ts: u64| since == 0 || ts > since;
+
+        let mut changes = SyncChanges {
+            server_time,
+            since,
+            history: data
+                .history
+                .values()
+                .filter(|h| changed(h.updated_at))
+                .cloned()
+                .collect(),
+            watchlist: data
+                .watchlist
+                .iter()
+                .filter(|w| changed(w.modified_at))
+                .cloned()
+                .collect(),
+            subs: data
+                .subs
+                .iter()
+                .filter(|s| changed(s.modified_at))
+                .cloned()
+                .collect(),
+            removed: SyncRemovals::default(),
+            markers: SyncMarkers {
+                history: data
+                    .history
+                    .values()
+                    .map(|h| h.updated_at)
+                    .max()
+                    .unwrap_or(0),
+                watchlist: data
+                    .watchlist
+                    .iter()
+                    .map(|w| w.modified_at)
+                    .max()
+                    .unwrap_or(0),
+                subs: data.subs.iter().map(|s| s.modified_at).max().unwrap_or(0),
+            },
+        };
+
+        for tombstone in &data.sync_tombstones {
+            let (removed, marker) = match tombstone.collection {
+                SyncCollection::Watchlist => (
+                    &mut changes.removed.watchlist,
+                    &mut changes.markers.watchlist,
+                ),
+                SyncCollection::Subs => (&mut changes.removed.subs, &mut changes.markers.subs),
+            };
+            *marker = (*marker).max(tombstone.removed_at);
+            if tombstone.removed_at > since {
+                removed.push(tombstone.key.clone());
+            }
+        }
+
+        changes
+            .history
+            .sort_by(|a, b| a.updated_at.cmp(&b.updated_at));
+        Ok(changes)
+    }
+
     // ── Twitch token (kept server-side only, never serialised to API) ─────────
 
     pub async fn get_twitch_token(&self) -> Option<String> {
@@ -505,6 +957,94 @@ impl HistoryStore {
         (history, subs)
     }
 
+    // ── Hidden trending VODs ─────────────────────────────────────────────────
+
+    pub async fn get_hidden_vods(&self) -> Vec<String> {
+        self.data.read().await.hidden_vods.clone()
+    }
+
+    /// Returns `true` if the id was not hidden yet. Past [`MAX_HIDDEN_VODS`]
+    /// the oldest entries are dropped.
+    pub async fn hide_vod(&self, vod_id: &str) -> AppResult<bool> {
+        {
+            let mut data = self.data.write().await;
+            if data.hidden_vods.iter().any(|id| id == vod_id) {
+                return Ok(false);
+            }
+            data.hidden_vods.push(vod_id.to_string());
+            let overflow = data.hidden_vods.len().saturating_sub(MAX_HIDDEN_VODS);
+            data.hidden_vods.drain(..overflow);
+        }
+        self.schedule_save();
+        Ok(true)
+    }
+
+    pub async fn unhide_vod(&self, vod_id: &str) -> AppResult<()> {
+        let removed = {
+            let mut data = self.data.write().await;
+            let initial_len = data.hidden_vods.len();
+            data.hidden_vods.retain(|id| id != vod_id);
+            data.hidden_vods.len() != initial_len
+        };
+        if removed {
+            self.schedule_save();
+        }
+        Ok(())
+    }
+
+    // ── Blocklist ────────────────────────────────────────────────────────────
+
+    /// Adds (`blocked`) or removes a channel and/or game, case-insensitively.
+    /// Returns the updated `(channels, games)`.
+    pub async fn update_blocklist(
+        &self,
+        channel: Option<&str>,
+        game: Option<&str>,
+        blocked: bool,
+    ) -> AppResult<(Vec<String>, Vec<String>)> {
+        let mut changed = false;
+        let lists = {
+            let mut data = self.data.write().await;
+            let settings = &mut data.settings;
+            let targets = [
+                (&mut settings.blocked_channels, channel),
+                (&mut settings.blocked_games, game),
+            ];
+            // Check every cap first, so a rejected request changes neither list.
+            let full = targets.iter().any(|(list, name)| {
+                name.is_some_and(|name| {
+                    blocked
+                        && list.len() >= MAX_BLOCKLIST_ENTRIES
+                        && !list.iter().any(|n| n.eq_ignore_ascii_case(name))
+                })
+            });
+            if full {
+                return Err(AppError::BadRequest(format!(
+                    "at most {MAX_BLOCKLIST_ENTRIES} entries can be blocked"
+                )));
+            }
+            for (list, name) in targets {
+                let Some(name) = name else { continue };
+                let present = list.iter().any(|n| n.eq_ignore_ascii_case(name));
+                if blocked && !present {
+                    list.push(name.to_string());
+                    changed = true;
+                } else if !blocked && present {
+                    list.retain(|n| !n.eq_ignore_ascii_case(name));
+                    changed = true;
+                }
+            }
+            (
+                settings.blocked_channels.clone(),
+                settings.blocked_games.clone(),
+            )
+        };
+        if changed {
+            self.schedule_save();
+        }
+        Ok(lists)
+    }
+
     pub async fn update_import_follows_setting(&self, value: bool) -> AppResult<()> {
         {
             let mut data = self.data.write().await;
@@ -616,6 +1156,159 @@ impl HistoryStore {
     }
 }
 
+pub(crate) fn now_ms() -> AppResult<u64> {
+    Ok(SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|e| AppError::Internal(e.to_string()))?
+        .as_millis() as u64)
+}
+
+/// Removal markers are only needed until every device has synced past them,
+/// so keep the most recent ones rather than growing the file forever.
+const MAX_SYNC_TOMBSTONES: usize = 500;
+
+/// Keeps `history.json` and the trends fingerprint bounded.
+const MAX_HIDDEN_VODS: usize = 1000;
+
+fn record_tombstone(data: &mut PersistedData, collection: SyncCollection, key: &str, now: u64) {
+    clear_tombstone(data, collection, key);
+    data.sync_tombstones.push(SyncTombstone {
+        collection,
+        key: key.to_string(),
+        removed_at: now,
+    });
+    let overflow = data
+        .sync_tombstones
+        .len()
+        .saturating_sub(MAX_SYNC_TOMBSTONES);
+    data.sync_tombstones.drain(..overflow);
+}
+
+fn clear_tombstone(data: &mut PersistedData, collection: SyncCollection, key: &str) {
+    data.sync_tombstones
+        .retain(|t| !(t.collection == collection && t.key == key));
+}
+
+/// Rejects non-finite progress (it would poison scoring and JSON output) and
+/// clamps the rest: negatives to 0, and `timecode` to `duration` when the
+/// duration is known, since players overshoot by a rounding error.
+fn sanitize_progress(timecode: f64, duration: f64) -> AppResult<(f64, f64)> {
+    if !timecode.is_finite() || !duration.is_finite() {
+        return Err(AppError::BadRequest(
+            "timecode and duration must be finite numbers".to_string(),
+        ));
+    }
+    let duration = duration.max(0.0);
+    let mut timecode = timecode.max(0.0);
+    if duration > 0.0 {
+        timecode = timecode.min(duration);
+    }
+    Ok((timecode, duration))
+}
+
+/// A single language code, or `None` when blank.
+fn normalize_preferred_language(raw: &str) -> AppResult<Option<String>> {
+    let mut languages = parse_language_list(raw)?;
+    if languages.len() > 1 {
+        return Err(AppError::BadRequest(
+            "preferredLanguage takes a single language".to_string(),
+        ));
+    }
+    Ok(languages.pop())
+}
+
+/// Accepts a bare token or one pasted with its `OAuth ` / `oauth:` prefix.
+/// Blank means "no token"; anything but ASCII alphanumerics is rejected so
+/// it can't smuggle extra header content.
+fn normalize_oauth_token(raw: &str) -> AppResult<Option<String>> {
+    let trimmed = raw.trim();
+    let token = ["oauth ", "oauth:"]
+        .iter()
+        .find_map(|prefix| {
+            trimmed
+                .get(..prefix.len())
+                .filter(|head| head.eq_ignore_ascii_case(prefix))
+                .map(|_| trimmed[prefix.len()..].trim())
+        })
+        .unwrap_or(trimmed);
+    if token.is_empty() {
+        return Ok(None);
+    }
+    if !token.chars().all(|c| c.is_ascii_alphanumeric()) {
+        return Err(AppError::BadRequest("Invalid OAuth token".to_string()));
+    }
+    Ok(Some(token.to_string()))
+}
+
+/// Lowercases language keys (VOD languages are compared lowercase) and
+/// rejects negative or non-finite multipliers, and foreign ratios outside
+/// `[0, 1]` or with `min > max`.
+fn sanitize_trending_preferences(mut prefs: TrendingPreferences) -> AppResult<TrendingPreferences> {
+    let ratios = [
+        Some(prefs.foreign_ratio_min),
+        Some(prefs.foreign_ratio_max),
+        prefs.foreign_ratio,
+    ];
+    if ratios
+        .into_iter()
+        .flatten()
+        .any(|r| !(0.0..=1.0).contains(&r))
+    {
+        return Err(AppError::BadRequest(
+            "foreign ratios must be between 0 and 1".to_string(),
+        ));
+    }
+    if prefs.foreign_ratio_min > prefs.foreign_ratio_max {
+        return Err(AppError::BadRequest(
+            "foreignRatioMin must not exceed foreignRatioMax".to_string(),
+        ));
+    }
+    let slots = [prefs.max_slots_favored, prefs.max_slots_other];
+    if slots.iter().any(|n| !(1..=MAX_CHANNEL_SLOTS).contains(n)) {
+        return Err(AppError::BadRequest(format!(
+            "maxSlotsFavored and maxSlotsOther must be between 1 and {MAX_CHANNEL_SLOTS}"
+        )));
+    }
+
+    let mut pinned_games: Vec<String> = Vec::new();
+    for game in std::mem::take(&mut prefs.pinned_games) {
+        let game = game.trim();
+        if !game.is_empty() && !pinned_games.iter().any(|g| g.eq_ignore_ascii_case(game)) {
+            pinned_games.push(game.to_string());
+        }
+    }
+    if pinned_games.len() > MAX_PINNED_GAMES {
+        return Err(AppError::BadRequest(format!(
+            "at most {MAX_PINNED_GAMES} games can be pinned"
+        )));
+    }
+    prefs.pinned_games = pinned_games;
+
+    let mut language_multipliers = HashMap::new();
+    for (language, multiplier) in std::mem::take(&mut prefs.language_multipliers) {
+        if !multiplier.is_finite() || multiplier < 0.0 {
+            return Err(AppError::BadRequest(format!(
+                "language multiplier for '{language}' must be a non-negative number"
+            )));
+        }
+        let language = language.trim().to_lowercase();
+        if !language.is_empty() {
+            language_multipliers.insert(language, multiplier);
+        }
+    }
+    prefs.language_multipliers = language_multipliers;
+    Ok(prefs)
+}
+
+fn validate_qr_preferences(prefs: QrPreferences) -> AppResult<QrPreferences> {
+    if !(QR_MIN_SIZE..=QR_MAX_SIZE).contains(&prefs.size) {
+        return Err(AppError::BadRequest(format!(
+            "QR size must be between {QR_MIN_SIZE} and {QR_MAX_SIZE} pixels"
+        )));
+    }
+    Ok(prefs)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -664,6 +1357,7 @@ mod tests {
             preview_thumbnail_url: "http://example.com/thumb.jpg".to_string(),
             length_seconds: 1200,
             added_at: 0,
+            modified_at: 0,
         };
         store.add_to_watchlist(entry).await.unwrap();
         let watchlist = store.get_watchlist().await;
@@ -678,10 +1372,550 @@ mod tests {
             login: "testuser".to_string(),
             display_name: "TestUser".to_string(),
             profile_image_url: "http://example.com/avatar.png".to_string(),
+            modified_at: 0,
         };
         store.add_sub(sub).await.unwrap();
         let subs = store.get_subs().await;
         assert_eq!(subs.len(), 1);
         assert_eq!(subs[0].login, "testuser");
     }
+
+    #[tokio::test]
+    async fn sub_metadata_updates_only_touch_changed_subs() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+        let sub = |login: &str, name: &str| SubEntry {
+            login: login.to_string(),
+            display_name: name.to_string(),
+            profile_image_url: format!("http://example.com/{login}.png"),
+            modified_at: 0,
+        };
+        store
+            .add_subs_bulk(vec![sub("alpha", "Alpha"), sub("bravo", "Bravo")])
+            .await
+            .unwrap();
+
+        let updated = store
+            .update_sub_metadata(vec![
+                sub("alpha", "AlphaRenamed"),
+                sub("bravo", "Bravo"),
+                sub("charlie", "Charlie"),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(updated, 1);
+
+        let subs = store.get_subs().await;
+        assert_eq!(subs.len(), 2);
+        assert_eq!(subs[0].display_name, "AlphaRenamed");
+        assert_eq!(subs[1].display_name, "Bravo");
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn concurrent_watchlist_adds_are_deduplicated() {
+        let dir = tempdir().unwrap();
+        let store = std::sync::Arc::new(HistoryStore::load(dir.path().to_path_buf()).unwrap());
+        let entry = WatchlistEntry {
+            vod_id: "vod789".to_string(),
+            title: "Retried VOD".to_string(),
+            preview_thumbnail_url: "http://example.com/thumb.jpg".to_string(),
+            length_seconds: 1200,
+            added_at: 0,
+            modified_at: 0,
+        };
+
+        let first = tokio::spawn({
+            let store = store.clone();
+            let entry = entry.clone();
+            async move { store.add_to_watchlist(entry).await.unwrap() }
+        });
+        let second = tokio::spawn({
+            let store = store.clone();
+            let entry = entry.clone();
+            async move { store.add_to_watchlist(entry).await.unwrap() }
+        });
+        let (first, second) = (first.await.unwrap(), second.await.unwrap());
+
+        assert!(first.1 ^ second.1, "exactly one add must report `added`");
+        assert_eq!(first.0.added_at, second.0.added_at);
+        assert_eq!(store.get_watchlist().await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn update_history_rejects_non_finite_progress() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+
+        for (timecode, duration) in [
+            (f64::NAN, 100.0),
+            (10.0, f64::NAN),
+            (f64::INFINITY, 100.0),
+            (10.0, f64::NEG_INFINITY),
+        ] {
+            let err = store.update_history("vod1", timecode, duration).await;
+            assert!(matches!(err, Err(AppError::BadRequest(_))));
+        }
+        assert!(store.get_history_by_vod_id("vod1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn update_history_clamps_negative_and_overshooting_values() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+
+        let entry = store.update_history("vod1", -5.0, -30.0).await.unwrap();
+        assert_eq!((entry.timecode, entry.duration), (0.0, 0.0));
+
+        let entry = store.update_history("vod2", 3600.4, 3600.0).await.unwrap();
+        assert_eq!((entry.timecode, entry.duration), (3600.0, 3600.0));
+
+        // Unknown duration leaves the timecode untouched.
+        let entry = store.update_history("vod3", 42.0, 0.0).await.unwrap();
+        assert_eq!((entry.timecode, entry.duration), (42.0, 0.0));
+    }
+
+    #[tokio::test]
+    async fn sync_changes_only_include_items_modified_after_since() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+        let watch = |vod_id: &str| WatchlistEntry {
+            vod_id: vod_id.to_string(),
+            title: format!("VOD {vod_id}"),
+            preview_thumbnail_url: "http://example.com/thumb.jpg".to_string(),
+            length_seconds: 600,
+            added_at: 0,
+            modified_at: 0,
+        };
+        let sub = |login: &str| SubEntry {
+            login: login.to_string(),
+            display_name: login.to_uppercase(),
+            profile_image_url: "http://example.com/avatar.png".to_string(),
+            modified_at: 0,
+        };
+
+        store.update_history("old-vod", 10.0, 100.0).await.unwrap();
+        store.add_to_watchlist(watch("old-watch")).await.unwrap();
+        store.add_to_watchlist(watch("doomed")).await.unwrap();
+        store.add_sub(sub("oldsub")).await.unwrap();
+
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        let since = store.get_sync_changes(0).await.unwrap().server_time;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+
+        store.update_history("new-vod", 20.0, 100.0).await.unwrap();
+        store.add_to_watchlist(watch("new-watch")).await.unwrap();
+        store.remove_from_watchlist("doomed").await.unwrap();
+        store.add_sub(sub("newsub")).await.unwrap();
+        store.remove_sub("oldsub").await.unwrap();
+
+        let delta = store.get_sync_changes(since).await.unwrap();
+        assert!(delta.server_time >= since);
+        let history: Vec<&str> = delta.history.iter().map(|h| h.vod_id.as_str()).collect();
+        let watchlist: Vec<&str> = delta.watchlist.iter().map(|w| w.vod_id.as_str()).collect();
+        let subs: Vec<&str> = delta.subs.iter().map(|s| s.login.as_str()).collect();
+        assert_eq!(history, vec!["new-vod"]);
+        assert_eq!(watchlist, vec!["new-watch"]);
+        assert_eq!(subs, vec!["newsub"]);
+        assert_eq!(delta.removed.watchlist, vec!["doomed".to_string()]);
+        assert_eq!(delta.removed.subs, vec!["oldsub".to_string()]);
+        assert!(delta.markers.history > since);
+        assert!(delta.markers.watchlist > since);
+        assert!(delta.markers.subs > since);
+
+        // A full sync still ships everything that's currently stored.
+        let full = store.get_sync_changes(0).await.unwrap();
+        assert_eq!(full.history.len(), 2);
+        assert_eq!(full.watchlist.len(), 2);
+        assert_eq!(full.subs.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn clear_subs_empties_subs_without_touching_history_or_watchlist() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+        store.update_history("vod1", 10.0, 100.0).await.unwrap();
+        store
+            .add_to_watchlist(WatchlistEntry {
+                vod_id: "vod2".to_string(),
+                title: "Kept".to_string(),
+                preview_thumbnail_url: "http://example.com/thumb.jpg".to_string(),
+                length_seconds: 600,
+                added_at: 0,
+                modified_at: 0,
+            })
+            .await
+            .unwrap();
+        for login in ["alpha", "bravo", "charlie"] {
+            store
+                .add_sub(SubEntry {
+                    login: login.to_string(),
+                    display_name: login.to_uppercase(),
+                    profile_image_url: "http://example.com/avatar.png".to_string(),
+                    modified_at: 0,
+                })
+                .await
+                .unwrap();
+        }
+        assert_eq!(store.subs_count().await, 3);
+
+        assert_eq!(store.clear_subs().await.unwrap(), 3);
+        assert_eq!(store.subs_count().await, 0);
+        assert!(store.get_subs().await.is_empty());
+        assert_eq!(store.get_all_history().await.len(), 1);
+        assert_eq!(store.get_watchlist().await.len(), 1);
+
+        // Other devices learn about the clear through the sync feed.
+        let changes = store.get_sync_changes(1).await.unwrap();
+        assert_eq!(changes.removed.subs.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn ephemeral_store_ignores_and_never_writes_history_file() {
+        let dir = tempdir().unwrap();
+        let file = dir.path().join("history.json");
+        let on_disk =
+            r#"{"history":{"999":{"vodId":"999","timecode":1.0,"duration":2.0,"updatedAt":1}}}"#;
+        std::fs::write(&file, on_disk).unwrap();
+
+        let store = HistoryStore::in_memory(dir.path().to_path_buf());
+        assert!(store.is_ephemeral());
+        assert!(store.get_all_history().await.is_empty());
+
+        store.update_history("123", 10.0, 60.0).await.unwrap();
+        store.hide_vod("456").await.unwrap();
+        assert!(store.get_history_by_vod_id("123").await.is_some());
+
+        assert!(!store.dirty.load(Ordering::SeqCst));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), on_disk);
+    }
+
+    #[tokio::test]
+    async fn marking_watchlist_watched_moves_it_to_history() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::in_memory(dir.path().to_path_buf());
+        for (vod_id, length) in [("111", 5400), ("222", 1200)] {
+            store
+                .add_to_watchlist(WatchlistEntry {
+                    vod_id: vod_id.to_string(),
+                    title: format!("VOD {vod_id}"),
+                    preview_thumbnail_url: String::new(),
+                    length_seconds: length,
+                    added_at: 0,
+                    modified_at: 0,
+                })
+                .await
+                .unwrap();
+        }
+        store.update_history("222", 300.0, 1250.0).await.unwrap();
+
+        let remaining = store.mark_watchlist_watched("111").await.unwrap().unwrap();
+        assert_eq!(remaining.len(), 1);
+        let watched = store.get_history_by_vod_id("111").await.unwrap();
+        assert_eq!((watched.timecode, watched.duration), (5400.0, 5400.0));
+
+        // A duration already reported by the player beats the snapshot.
+        store.mark_watchlist_watched("222").await.unwrap();
+        let watched = store.get_history_by_vod_id("222").await.unwrap();
+        assert_eq!((watched.timecode, watched.duration), (1250.0, 1250.0));
+
+        assert!(store.mark_watchlist_watched("333").await.unwrap().is_none());
+        let changes = store.get_sync_changes(0).await.unwrap();
+        assert_eq!(changes.removed.watchlist.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn etag_changes_on_writes_only() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::in_memory(dir.path().to_path_buf());
+        let initial = store.etag();
+
+        store.get_all_history().await;
+        assert_eq!(store.etag(), initial);
+
+        store.update_history("123", 10.0, 60.0).await.unwrap();
+        assert_ne!(store.etag(), initial);
+    }
+
+    #[tokio::test]
+    async fn flush_writes_pending_changes_without_waiting_for_the_debounce() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+        store.update_history("123", 10.0, 60.0).await.unwrap();
+
+        store.flush().await.unwrap();
+        assert!(!store.dirty.load(Ordering::SeqCst));
+
+        let reloaded = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+        assert!(reloaded.get_history_by_vod_id("123").await.is_some());
+        assert!(!dir.path().join("history.json.tmp").exists());
+    }
+
+    #[tokio::test]
+    async fn load_normalizes_or_drops_non_numeric_vod_ids() {
+        let dir = tempdir().unwrap();
+        let on_disk = r#"{
+            "history": {
+                "v123": {"vodId":"v123","timecode":5.0,"duration":60.0,"updatedAt":2},
+                "123": {"vodId":"123","timecode":1.0,"duration":60.0,"updatedAt":1},
+                "vod1": {"vodId":"vod1","timecode":1.0,"duration":60.0,"updatedAt":1},
+                "456": {"vodId":"456","timecode":1.0,"duration":60.0,"updatedAt":1}
+            },
+            "watchlist": [
+                {"vodId":"v789","title":"a","previewThumbnailURL":"","lengthSeconds":1},
+                {"vodId":"789","title":"b","previewThumbnailURL":"","lengthSeconds":1},
+                {"vodId":"vod456","title":"c","previewThumbnailURL":"","lengthSeconds":1}
+            ]
+        }"#;
+        std::fs::write(dir.path().join("history.json"), on_disk).unwrap();
+
+        let store = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+        let history = store.get_all_history().await;
+        let mut ids: Vec<&str> = history.keys().map(String::as_str).collect();
+        ids.sort_unstable();
+        assert_eq!(ids, ["123", "456"]);
+        assert_eq!(history["123"].vod_id, "123");
+        assert_eq!(history["123"].timecode, 5.0);
+
+        let watchlist = store.get_watchlist().await;
+        assert_eq!(watchlist.len(), 1);
+        assert_eq!(
+            (watchlist[0].vod_id.as_str(), watchlist[0].title.as_str()),
+            ("789", "a")
+        );
+        assert!(store.dirty.load(Ordering::SeqCst));
+    }
+
+    #[tokio::test]
+    async fn flush_waits_for_a_save_already_in_progress() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+        store.update_history("123", 10.0, 60.0).await.unwrap();
+
+        // Stand in for the background saver between its `dirty` check and
+        // the end of its write.
+        let guard = store.save_lock.clone().lock_owned().await;
+        assert!(store.dirty.swap(false, Ordering::SeqCst));
+        let flush = store.flush();
+        tokio::pin!(flush);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(50), &mut flush)
+                .await
+                .is_err(),
+            "flush returned while a save was still writing"
+        );
+
+        drop(guard);
+        flush.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn history_and_watchlist_writes_notify_subscribers() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::in_memory(dir.path().to_path_buf());
+        // Writes with nobody listening must not fail.
+        store.update_history("1", 10.0, 60.0).await.unwrap();
+
+        let mut events = store.subscribe();
+        store.update_history("1", 20.0, 60.0).await.unwrap();
+        store
+            .add_to_watchlist(WatchlistEntry {
+                vod_id: "2".to_string(),
+                title: "VOD".to_string(),
+                preview_thumbnail_url: String::new(),
+                length_seconds: 60,
+                added_at: 0,
+                modified_at: 0,
+            })
+            .await
+            .unwrap();
+        store.remove_from_watchlist("missing").await.unwrap();
+        store.mark_watchlist_watched("2").await.unwrap();
+
+        let mut received = Vec::new();
+        while let Ok(event) = events.try_recv() {
+            received.push(event);
+        }
+        assert_eq!(
+            received,
+            [
+                StoreEvent::HistoryUpdated,
+                StoreEvent::WatchlistUpdated,
+                StoreEvent::WatchlistUpdated,
+                StoreEvent::HistoryUpdated,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn qr_preferences_are_range_checked_and_default_when_missing() {
+        use crate::server::types::QrErrorCorrection;
+
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::in_memory(dir.path().to_path_buf());
+        assert_eq!(store.get_settings().await.qr, QrPreferences::default());
+
+        let set_qr = |qr: QrPreferences| {
+            store.update_settings(
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(qr),
+                None,
+                None,
+                None,
+                None,
+            )
+        };
+        let qr: QrPreferences =
+            serde_json::from_value(serde_json::json!({ "size": 800, "errorCorrection": "H" }))
+                .unwrap();
+        let settings = set_qr(qr).await.unwrap();
+        assert_eq!(settings.qr.size, 800);
+        assert_eq!(settings.qr.error_correction, QrErrorCorrection::H);
+
+        let too_small = QrPreferences {
+            size: 50,
+            ..QrPreferences::default()
+        };
+        assert!(set_qr(too_small).await.is_err());
+        assert_eq!(store.get_settings().await.qr.size, 800);
+    }
+
+    #[test]
+    fn trending_slot_caps_are_range_checked() {
+        let prefs = |favored: usize, other: usize| TrendingPreferences {
+            max_slots_favored: favored,
+            max_slots_other: other,
+            ..TrendingPreferences::default()
+        };
+        assert!(sanitize_trending_preferences(prefs(6, 1)).is_ok());
+        assert!(sanitize_trending_preferences(prefs(0, 2)).is_err());
+        assert!(sanitize_trending_preferences(prefs(4, 11)).is_err());
+
+        let defaults = TrendingPreferences::default();
+        assert_eq!(defaults.channel_slots(true, false), 4);
+        assert_eq!(defaults.channel_slots(false, true), 3);
+        assert_eq!(defaults.channel_slots(false, false), 2);
+        // Related channels never outrank favorites.
+        assert_eq!(prefs(2, 2).channel_slots(false, true), 2);
+    }
+
+    #[tokio::test]
+    async fn blocklist_edits_ignore_case() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+
+        store
+            .update_blocklist(Some("loudguy"), Some("Slots"), true)
+            .await
+            .unwrap();
+        let (channels, games) = store
+            .update_blocklist(None, Some("slots"), true)
+            .await
+            .unwrap();
+        assert_eq!((channels.len(), games.len()), (1, 1));
+
+        let (channels, games) = store
+            .update_blocklist(Some("LOUDGUY"), None, false)
+            .await
+            .unwrap();
+        assert!(channels.is_empty());
+        assert_eq!(games, ["Slots"]);
+        assert_eq!(store.get_settings().await.blocked_games, ["Slots"]);
+    }
+
+    #[tokio::test]
+    async fn blocklist_rejects_a_full_list_without_a_partial_write() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+        for i in 0..MAX_BLOCKLIST_ENTRIES {
+            store
+                .update_blocklist(None, Some(&format!("game{i}")), true)
+                .await
+                .unwrap();
+        }
+
+        let err = store
+            .update_blocklist(Some("loudguy"), Some("one too many"), true)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, AppError::BadRequest(_)));
+        assert!(store.get_settings().await.blocked_channels.is_empty());
+
+        // Re-blocking an entry already on a full list is still fine.
+        store
+            .update_blocklist(Some("loudguy"), Some("GAME0"), true)
+            .await
+            .unwrap();
+    }
+
+    #[test]
+    fn pinned_games_are_trimmed_deduplicated_and_capped() {
+        let prefs = |games: &[&str]| TrendingPreferences {
+            pinned_games: games.iter().map(|g| g.to_string()).collect(),
+            ..TrendingPreferences::default()
+        };
+        let sanitized =
+            sanitize_trending_preferences(prefs(&[" Dota 2 ", "dota 2", "", "Minecraft"])).unwrap();
+        assert_eq!(sanitized.pinned_games, ["Dota 2", "Minecraft"]);
+        assert!(sanitize_trending_preferences(prefs(&["a", "b", "c", "d", "e", "f"])).is_err());
+    }
+
+    #[tokio::test]
+    async fn oauth_token_is_normalized_encrypted_on_disk_and_redacted() {
+        let dir = tempdir().unwrap();
+        let store = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+        let set_token = |token: Option<&str>| {
+            store.update_settings(
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                None,
+                Some(token.map(str::to_string)),
+                None,
+                None,
+                None,
+            )
+        };
+
+        assert!(set_token(Some("abc def")).await.is_err());
+        let settings = set_token(Some(" OAuth abc123xyz ")).await.unwrap();
+        assert_eq!(settings.oauth_token.as_deref(), Some("abc123xyz"));
+
+        let redacted = serde_json::to_value(settings.redacted()).unwrap();
+        assert!(redacted.get("oauthToken").is_none());
+        assert_eq!(redacted["hasOauthToken"], true);
+
+        let guard = store.save_lock.clone().lock_owned().await;
+        HistoryStore::perform_save(&store.data, &store.file_path, &store.token_key, guard)
+            .await
+            .unwrap();
+        let on_disk = std::fs::read_to_string(dir.path().join("history.json")).unwrap();
+        assert!(!on_disk.contains("abc123xyz"));
+        let reloaded = HistoryStore::load(dir.path().to_path_buf()).unwrap();
+        assert_eq!(
+            reloaded.get_settings().await.oauth_token.as_deref(),
+            Some("abc123xyz")
+        );
+
+        assert!(set_token(Some("  ")).await.unwrap().oauth_token.is_none());
+    }
 }
@@ -1,5 +1,30 @@
 use super::error::{AppError, AppResult};
 use reqwest::Client;
+use serde_json::Value;
+
+/// Decodes a text body (playlists, error pages) as UTF-8, replacing invalid
+/// sequences instead of failing: a stray byte must not break a whole playlist.
+pub fn decode_text_lossy(bytes: &[u8]) -> String {
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+/// Parses a GQL response body strictly, so that encoding problems and malformed
+/// JSON surface as distinct, readable errors.
+pub fn parse_gql_json(bytes: &[u8]) -> AppResult<Value> {
+    let text = std::str::from_utf8(bytes).map_err(|e| {
+        AppError::TwitchApi(format!(
+            "GQL response is not valid UTF-8 (invalid byte at offset {})",
+            e.valid_up_to()
+        ))
+    })?;
+
+    serde_json::from_str(text.trim_start_matches('\u{feff}')).map_err(|e| {
+        AppError::TwitchApi(format!(
+            "GQL response is not valid JSON ({} bytes): {e}",
+            bytes.len()
+        ))
+    })
+}
 
 pub async fn get_text_checked(client: &Client, url: &str) -> AppResult<String> {
     let resp = client
@@ -15,9 +40,12 @@ pub async fn get_text_checked(client: &Client, url: &str) -> AppResult<String> {
         )));
     }
 
-    resp.text()
+    let bytes = resp
+        .bytes()
         .await
-        .map_err(|e| AppError::Internal(format!("Reading response from {url}: {e}")))
+        .map_err(|e| AppError::Internal(format!("Reading response from {url}: {e}")))?;
+
+    Ok(decode_text_lossy(&bytes))
 }
 
 pub async fn get_bytes_checked(client: &Client, url: &str) -> AppResult<bytes::Bytes> {
@@ -47,8 +75,9 @@ pub async fn get_text_with_direct_fallback(
 ) -> AppResult<String> {
     match primary_client.get(url).send().await {
         Ok(resp) if resp.status().is_success() => resp
-            .text()
+            .bytes()
             .await
+            .map(|bytes| decode_text_lossy(&bytes))
             .map_err(|e| AppError::Internal(e.to_string())),
         Ok(resp) => {
             eprintln!(
@@ -83,6 +112,16 @@ mod tests {
         let app = Router::new()
             .route("/text", get(|| async { "hello world" }))
             .route("/bytes", get(|| async { vec![1u8, 2, 3] }))
+            .route(
+                "/latin1-playlist",
+                get(|| async {
+                    // "#EXTM3U\n#EXT-X-TITLE:caf\xe9\n" with a Latin-1 encoded "é"
+                    let mut body = b"#EXTM3U\n#EXT-X-TITLE:caf".to_vec();
+                    body.push(0xE9);
+                    body.extend_from_slice(b"\nsegment0.ts\n");
+                    body
+                }),
+            )
             .route(
                 "/error",
                 get(|| async { axum::http::StatusCode::INTERNAL_SERVER_ERROR }),
@@ -154,4 +193,38 @@ mod tests {
 
         assert_eq!(res, "hello world");
     }
+
+    #[tokio::test]
+    async fn get_text_checked_decodes_invalid_utf8_lossily() {
+        let base_url = spawn_test_server().await;
+        let client = Client::new();
+        let res = get_text_checked(&client, &format!("{}/latin1-playlist", base_url))
+            .await
+            .unwrap();
+
+        assert!(res.starts_with("#EXTM3U\n"));
+        assert!(res.contains("caf\u{FFFD}"));
+        assert!(res.contains("segment0.ts"));
+    }
+
+    #[test]
+    fn parse_gql_json_reports_invalid_utf8_clearly() {
+        let res = parse_gql_json(b"{\"data\":\"\xff\"}");
+        match res {
+            Err(AppError::TwitchApi(msg)) => {
+                assert!(msg.contains("not valid UTF-8"), "{msg}");
+                assert!(msg.contains("offset 9"), "{msg}");
+            }
+            other => panic!("Expected AppError::TwitchApi, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn parse_gql_json_accepts_bom_and_reports_bad_json() {
+        let value = parse_gql_json("\u{feff}{\"data\":{\"ok\":true}}".as_bytes()).unwrap();
+        assert_eq!(value["data"]["ok"], true);
+
+        let res = parse_gql_json(b"<html>rate limited</html>");
+        assert!(matches!(res, Err(AppError::TwitchApi(msg)) if msg.contains("not valid JSON")));
+    }
 }
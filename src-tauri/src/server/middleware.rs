@@ -1,12 +1,25 @@
-use axum::{extract::State, middleware::Next, response::Response};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-#[cfg(not(debug_assertions))]
 use axum::{
-    http::{header, StatusCode},
-    response::IntoResponse,
+    extract::State,
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
     Json,
 };
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use tracing::warn;
+
+#[cfg(not(debug_assertions))]
+use axum::extract::ConnectInfo;
+#[cfg(not(debug_assertions))]
+use std::net::SocketAddr;
 
+#[cfg(not(debug_assertions))]
+use super::client_ip::client_ip as resolve_client_ip;
+use super::config::ServerConfig;
 use super::state::ApiState;
 
 /// Validates requests carry a valid server token via the `X-NSV-Token` header
@@ -49,11 +62,11 @@ pub async fn auth_middleware(
         .map(|s| s.chars().take(240).collect::<String>());
 
     let client_ip = req
-        .headers()
-        .get("x-forwarded-for")
-        .and_then(|v| v.to_str().ok())
-        .map(|raw| raw.split(',').next().unwrap_or("").trim().to_string())
-        .filter(|s| !s.is_empty());
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| {
+            resolve_client_ip(req.headers(), peer.ip(), &state.config.trusted_proxies).to_string()
+        });
 
     let token_from_header = req
         .headers()
@@ -61,16 +74,18 @@ pub async fn auth_middleware(
         .and_then(|v| v.to_str().ok())
         .map(|s| s.to_string());
 
-    let token_from_query = req.uri().query().and_then(|q| {
-        q.split('&').find_map(|pair| {
-            let mut parts = pair.splitn(2, '=');
-            if parts.next() == Some("t") {
-                parts.next().map(|v| v.to_string())
-            } else {
-                None
-            }
+    // `t` can appear twice on a shared master playlist link (`?t=600` start
+    // offset plus the token), so any `t` value may carry the token.
+    let query_tokens: Vec<String> = req
+        .uri()
+        .query()
+        .map(|q| {
+            q.split('&')
+                .filter_map(|pair| pair.strip_prefix("t="))
+                .map(|v| v.to_string())
+                .collect()
         })
-    });
+        .unwrap_or_default();
 
     let query_device_id = req
         .uri()
@@ -97,9 +112,10 @@ pub async fn auth_middleware(
 
     let device_id = header_device_id.or(query_device_id);
 
-    let provided = token_from_header.or(token_from_query);
-
-    let token_ok = provided.as_deref() == Some(&state.server_token);
+    let token_ok = match token_from_header.as_deref() {
+        Some(token) => token == state.server_token,
+        None => query_tokens.iter().any(|t| *t == state.server_token),
+    };
     let device_trusted = if token_ok {
         false
     } else if let Some(id) = device_id.as_deref() {
@@ -111,7 +127,7 @@ pub async fn auth_middleware(
     if !token_ok && !device_trusted {
         return (
             StatusCode::UNAUTHORIZED,
-            Json(serde_json::json!({ "error": "Unauthorized" })),
+            Json(serde_json::json!({ "error": "Unauthorized", "code": "UNAUTHORIZED" })),
         )
             .into_response();
     }
@@ -126,27 +142,436 @@ pub async fn auth_middleware(
     next.run(req).await
 }
 
-pub async fn security_headers_middleware(req: axum::extract::Request, next: Next) -> Response {
+/// State for [`basic_auth_middleware`]: the configured password plus the
+/// per-session token the desktop window authenticates with.
+#[derive(Clone)]
+pub struct BasicAuthGate {
+    pub config: Arc<ServerConfig>,
+    pub server_token: String,
+}
+
+/// Shared-password gate for deployments reachable beyond the LAN. Runs in
+/// front of the per-device token check and leaves only `/api/health` open.
+pub async fn basic_auth_middleware(
+    State(gate): State<BasicAuthGate>,
+    req: axum::extract::Request,
+    next: Next,
+) -> Response {
+    let Some(basic_auth) = gate.config.basic_auth.as_ref() else {
+        return next.run(req).await;
+    };
+    if req.uri().path() == "/api/health" || is_desktop_screenshare(&req, &gate.server_token) {
+        return next.run(req).await;
+    }
+
+    let authorized = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(parse_basic_credentials)
+        .is_some_and(|(user, pass)| basic_auth.verify(&user, &pass));
+
+    if authorized {
+        return next.run(req).await;
+    }
+
+    (
+        StatusCode::UNAUTHORIZED,
+        [(
+            header::WWW_AUTHENTICATE,
+            HeaderValue::from_static(r#"Basic realm="NoSubVOD", charset="UTF-8""#),
+        )],
+        Json(serde_json::json!({ "error": "Unauthorized", "code": "UNAUTHORIZED" })),
+    )
+        .into_response()
+}
+
+/// The desktop window's screenshare WebSocket can't send Basic credentials,
+/// so it gets in with the session token instead. The peer address proves
+/// nothing: tunnels and local proxies connect over loopback too.
+fn is_desktop_screenshare(req: &axum::extract::Request, server_token: &str) -> bool {
+    req.uri().path() == "/api/screenshare/ws"
+        && req.uri().query().is_some_and(|q| {
+            q.split('&')
+                .filter_map(|pair| pair.strip_prefix("t="))
+                .any(|t| t == server_token)
+        })
+}
+
+fn parse_basic_credentials(header: &str) -> Option<(String, String)> {
+    let (scheme, encoded) = header.trim().split_once(' ')?;
+    if !scheme.eq_ignore_ascii_case("basic") {
+        return None;
+    }
+    let decoded = B64.decode(encoded.trim()).ok()?;
+    let decoded = String::from_utf8(decoded).ok()?;
+    let (user, pass) = decoded.split_once(':')?;
+    Some((user.to_string(), pass.to_string()))
+}
+
+/// Hardening headers for every response. Portal pages additionally get the
+/// configured CSP; `/api` responses (playlists, segment proxy, JSON) don't, as
+/// they're never rendered as documents and players fetch them cross-context.
+pub async fn security_headers_middleware(
+    State(config): State<Arc<ServerConfig>>,
+    req: axum::extract::Request,
+    next: Next,
+) -> Response {
     let path = req.uri().path().to_string();
     let mut response = next.run(req).await;
-    let headers = response.headers_mut();
-    headers.insert("x-content-type-options", "nosniff".parse().unwrap());
+    apply_security_headers(&path, response.headers_mut(), config.portal_csp.as_deref());
+    response
+}
+
+fn apply_security_headers(path: &str, headers: &mut HeaderMap, portal_csp: Option<&str>) {
+    let is_api = path == "/api" || path.starts_with("/api/");
+
+    headers.insert(
+        "x-content-type-options",
+        HeaderValue::from_static("nosniff"),
+    );
 
     // Extensions need to be embeddable in iframes within the portal.
     if path.starts_with("/api/extensions/") {
-        headers.insert("x-frame-options", "SAMEORIGIN".parse().unwrap());
+        headers.insert("x-frame-options", HeaderValue::from_static("SAMEORIGIN"));
     } else {
-        headers.insert("x-frame-options", "DENY".parse().unwrap());
+        headers.insert("x-frame-options", HeaderValue::from_static("DENY"));
     }
 
-    headers.insert("x-xss-protection", "1; mode=block".parse().unwrap());
-    headers.insert("referrer-policy", "no-referrer".parse().unwrap());
+    headers.insert(
+        "x-xss-protection",
+        HeaderValue::from_static("1; mode=block"),
+    );
+    headers.insert("referrer-policy", HeaderValue::from_static("no-referrer"));
     headers.insert(
         "permissions-policy",
-        "camera=(), microphone=(), geolocation=(), interest-cohort=()"
-            .parse()
-            .unwrap(),
+        HeaderValue::from_static("camera=(), microphone=(), geolocation=(), interest-cohort=()"),
+    );
+
+    if is_api {
+        // ETag-tagged payloads may be kept, but must be revalidated so the
+        // browser sends `If-None-Match` and can get a 304.
+        let cache_control = if headers.contains_key(header::ETAG) {
+            "no-cache, private"
+        } else {
+            "no-store, private"
+        };
+        headers.insert("cache-control", HeaderValue::from_static(cache_control));
+    } else {
+        // index.html names the hashed asset bundles, so a stale copy kept
+        // across an app update would point at files that no longer exist.
+        let is_html = headers
+            .get(header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/html"));
+        if is_html && !headers.contains_key(header::CACHE_CONTROL) {
+            headers.insert(header::CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+        }
+        if let Some(csp) = portal_csp.and_then(|csp| HeaderValue::from_str(csp).ok()) {
+            headers.insert("content-security-policy", csp);
+        }
+    }
+}
+
+/// Time a handler gets to produce its response headers. Bodies stream after
+/// that, so long segment and download transfers are never cut off.
+pub const REQUEST_TIMEOUT: Duration = Duration::from_secs(30);
+/// Master playlists probe several CDN URLs and the segment proxy waits on the
+/// CDN, so streaming routes get more room.
+pub const STREAM_REQUEST_TIMEOUT: Duration = Duration::from_secs(120);
+/// Routes that fan out to Twitch once per sub, VOD or category (imports of
+/// thousands of logins, a cold trends feed) and can't answer within
+/// [`REQUEST_TIMEOUT`].
+pub const FAN_OUT_REQUEST_TIMEOUT: Duration = Duration::from_secs(300);
+const FAN_OUT_ROUTES: &[&str] = &[
+    "/api/subs/import",
+    "/api/subs/refresh",
+    "/api/vods/batch",
+    "/api/trends",
+];
+
+/// Answers 408 when a handler takes longer than its route's deadline.
+pub async fn request_timeout_middleware(req: axum::extract::Request, next: Next) -> Response {
+    let Some(limit) = request_timeout_for(req.uri().path()) else {
+        return next.run(req).await;
+    };
+    match tokio::time::timeout(limit, next.run(req)).await {
+        Ok(response) => response,
+        Err(_) => (
+            StatusCode::REQUEST_TIMEOUT,
+            Json(serde_json::json!({ "error": "Request timed out", "code": "TIMEOUT" })),
+        )
+            .into_response(),
+    }
+}
+
+/// Handler time above which a request is logged as slow.
+pub const SLOW_REQUEST_THRESHOLD: Duration = Duration::from_secs(2);
+
+/// Sets `X-Response-Time-Ms` on every response, errors included, and logs
+/// slow requests. Measures the time to the response headers, so streamed
+/// bodies don't count.
+pub async fn response_time_middleware(req: axum::extract::Request, next: Next) -> Response {
+    let started = Instant::now();
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    let mut response = next.run(req).await;
+
+    let elapsed = started.elapsed();
+    response.headers_mut().insert(
+        "x-response-time-ms",
+        HeaderValue::from(elapsed.as_millis() as u64),
     );
-    headers.insert("cache-control", "no-store, private".parse().unwrap());
+    if elapsed > SLOW_REQUEST_THRESHOLD {
+        warn!(
+            "slow request: {method} {path} took {}ms ({})",
+            elapsed.as_millis(),
+            response.status()
+        );
+    }
     response
 }
+
+fn request_timeout_for(path: &str) -> Option<Duration> {
+    // The native folder picker waits on the user, not on us.
+    if path == "/api/system/dialog/folder" {
+        return None;
+    }
+    if path.ends_with(".m3u8") || path.ends_with(".ts") || path.starts_with("/api/downloads/hls/") {
+        return Some(STREAM_REQUEST_TIMEOUT);
+    }
+    if FAN_OUT_ROUTES.contains(&path) {
+        return Some(FAN_OUT_REQUEST_TIMEOUT);
+    }
+    Some(REQUEST_TIMEOUT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request, routing::get, Router};
+    use tower::ServiceExt;
+
+    use crate::server::portal::mount_portal_with;
+
+    async fn portal_app(config: ServerConfig) -> (Router, tempfile::TempDir) {
+        let dist = tempfile::tempdir().unwrap();
+        std::fs::write(dist.path().join("index.html"), "<!doctype html>").unwrap();
+
+        let api = Router::new().route("/vod/:id/master.m3u8", get(|| async { "#EXTM3U" }));
+        let router = mount_portal_with(
+            Router::new().nest("/api", api),
+            Some(dist.path().to_path_buf()),
+            false,
+        )
+        .layer(axum::middleware::from_fn_with_state(
+            Arc::new(config),
+            security_headers_middleware,
+        ));
+        (router, dist)
+    }
+
+    async fn get_headers(app: &Router, uri: &str) -> HeaderMap {
+        let response = app
+            .clone()
+            .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        response.headers().clone()
+    }
+
+    #[tokio::test]
+    async fn portal_responses_carry_nosniff_and_csp() {
+        let (app, _dist) = portal_app(ServerConfig::default()).await;
+
+        for uri in ["/", "/index.html", "/some/spa/route"] {
+            let headers = get_headers(&app, uri).await;
+            assert_eq!(headers["x-content-type-options"], "nosniff", "{uri}");
+            assert_eq!(headers["x-frame-options"], "DENY", "{uri}");
+            assert_eq!(headers["referrer-policy"], "no-referrer", "{uri}");
+            assert_eq!(headers[header::CACHE_CONTROL], "no-cache", "{uri}");
+            assert_eq!(
+                headers["content-security-policy"],
+                crate::server::config::DEFAULT_PORTAL_CSP,
+                "{uri}"
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn playlist_responses_skip_csp() {
+        let (app, _dist) = portal_app(ServerConfig::default()).await;
+
+        let headers = get_headers(&app, "/api/vod/123/master.m3u8").await;
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+        assert!(headers.get("content-security-policy").is_none());
+    }
+
+    #[tokio::test]
+    async fn portal_csp_can_be_disabled() {
+        let config = ServerConfig::from_lookup(|key| {
+            (key == "NOSUBVOD_PORTAL_CSP").then(|| "off".to_string())
+        });
+        let (app, _dist) = portal_app(config).await;
+
+        let headers = get_headers(&app, "/").await;
+        assert_eq!(headers["x-content-type-options"], "nosniff");
+        assert!(headers.get("content-security-policy").is_none());
+    }
+
+    #[tokio::test]
+    async fn every_response_reports_its_handler_time() {
+        let app = Router::new()
+            .route("/ok", get(|| async { "ok" }))
+            .route(
+                "/fail",
+                get(|| async { (StatusCode::BAD_GATEWAY, "upstream down") }),
+            )
+            .layer(axum::middleware::from_fn(response_time_middleware));
+
+        for uri in ["/ok", "/fail", "/missing"] {
+            let headers = get_headers(&app, uri).await;
+            let ms: u64 = headers["x-response-time-ms"]
+                .to_str()
+                .unwrap()
+                .parse()
+                .unwrap();
+            assert!(ms < 1000, "{uri}");
+        }
+    }
+
+    fn basic_auth_app() -> Router {
+        // sha256("hunter2")
+        let config = ServerConfig::from_lookup(|key| match key {
+            "NOSUBVOD_BASIC_AUTH_USER" => Some("family".to_string()),
+            "NOSUBVOD_BASIC_AUTH_SHA256" => {
+                Some("f52fbd32b2b3b86ff88ef6c490628285f482af15ddcb29541f94bcf526a3f6c7".to_string())
+            }
+            _ => None,
+        });
+        assert!(config.basic_auth.is_some());
+
+        Router::new()
+            .route("/api/health", get(|| async { "ok" }))
+            .route("/api/history", get(|| async { "[]" }))
+            .route("/api/screenshare/ws", get(|| async { "ws" }))
+            .layer(axum::middleware::from_fn_with_state(
+                BasicAuthGate {
+                    config: Arc::new(config),
+                    server_token: "session-token".to_string(),
+                },
+                basic_auth_middleware,
+            ))
+    }
+
+    async fn status_with_auth(app: &Router, uri: &str, auth: Option<&str>) -> Response {
+        let mut req = Request::builder().uri(uri);
+        if let Some(credentials) = auth {
+            req = req.header(
+                header::AUTHORIZATION,
+                format!("Basic {}", B64.encode(credentials)),
+            );
+        }
+        app.clone()
+            .oneshot(req.body(Body::empty()).unwrap())
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn basic_auth_accepts_correct_credentials_and_rejects_wrong_ones() {
+        let app = basic_auth_app();
+
+        let ok = status_with_auth(&app, "/api/history", Some("family:hunter2")).await;
+        assert_eq!(ok.status(), StatusCode::OK);
+
+        for credentials in [None, Some("family:wrong"), Some("intruder:hunter2")] {
+            let rejected = status_with_auth(&app, "/api/history", credentials).await;
+            assert_eq!(
+                rejected.status(),
+                StatusCode::UNAUTHORIZED,
+                "{credentials:?}"
+            );
+            assert!(rejected.headers()[header::WWW_AUTHENTICATE]
+                .to_str()
+                .unwrap()
+                .starts_with("Basic realm="));
+        }
+
+        // The health probe stays reachable without credentials.
+        let health = status_with_auth(&app, "/api/health", None).await;
+        assert_eq!(health.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn basic_auth_only_waives_the_password_for_the_desktop_screenshare_socket() {
+        let app = basic_auth_app();
+        let status_of = |uri: &'static str| {
+            let response = app.clone().oneshot(
+                Request::builder()
+                    .uri(uri)
+                    .extension(axum::extract::ConnectInfo(std::net::SocketAddr::from((
+                        [127, 0, 0, 1],
+                        50000,
+                    ))))
+                    .body(Body::empty())
+                    .unwrap(),
+            );
+            async move { response.await.unwrap().status() }
+        };
+
+        assert_eq!(
+            status_of("/api/screenshare/ws?t=session-token").await,
+            StatusCode::OK
+        );
+        // Loopback alone (a tunnel or local proxy) is not enough.
+        for uri in [
+            "/api/history",
+            "/api/history?t=session-token",
+            "/api/screenshare/ws",
+            "/api/screenshare/ws?t=wrong",
+        ] {
+            assert_eq!(status_of(uri).await, StatusCode::UNAUTHORIZED, "{uri}");
+        }
+    }
+
+    #[test]
+    fn streaming_routes_get_longer_timeouts_and_the_folder_picker_none() {
+        assert_eq!(request_timeout_for("/api/history"), Some(REQUEST_TIMEOUT));
+        assert_eq!(request_timeout_for("/"), Some(REQUEST_TIMEOUT));
+        for path in [
+            "/api/vod/123/master.m3u8",
+            "/api/stream/variant.m3u8",
+            "/api/stream/variant.ts",
+            "/api/downloads/hls/file.mp4",
+        ] {
+            assert_eq!(
+                request_timeout_for(path),
+                Some(STREAM_REQUEST_TIMEOUT),
+                "{path}"
+            );
+        }
+        assert_eq!(request_timeout_for("/api/system/dialog/folder"), None);
+    }
+
+    #[test]
+    fn fan_out_routes_outlast_the_default_timeout() {
+        for path in [
+            "/api/subs/import",
+            "/api/subs/refresh",
+            "/api/vods/batch",
+            "/api/trends",
+        ] {
+            assert_eq!(
+                request_timeout_for(path),
+                Some(FAN_OUT_REQUEST_TIMEOUT),
+                "{path}"
+            );
+        }
+        // Only the feed itself, not its cheap siblings.
+        assert_eq!(
+            request_timeout_for("/api/trends/hidden"),
+            Some(REQUEST_TIMEOUT)
+        );
+    }
+}
@@ -1,5 +1,9 @@
 pub mod auth;
+pub mod breaker;
+pub mod cache;
 pub mod chat;
+pub mod client_ip;
+pub mod config;
 pub mod download;
 pub mod download_paths;
 pub mod dto;
@@ -8,6 +12,10 @@ pub mod extensions;
 pub mod history;
 pub mod http_utils;
 pub mod middleware;
+pub mod network;
+pub mod opml;
+pub mod portal;
+pub mod qr;
 pub mod routes;
 pub mod screenshare;
 pub mod state;
@@ -17,48 +25,78 @@ pub mod url_utils;
 pub mod validation;
 
 use moka::future::Cache;
+use std::net::{IpAddr, Ipv4Addr};
 use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::Duration;
 
 #[cfg(not(debug_assertions))]
 use axum_server::tls_rustls::RustlsConfig;
-use base64::engine::general_purpose::STANDARD as B64;
-use base64::Engine;
-use image::ImageEncoder;
-use qrcode::QrCode;
 #[cfg(not(debug_assertions))]
 use rcgen::generate_simple_self_signed;
 use tauri::AppHandle;
 #[cfg(not(debug_assertions))]
 use tauri::Manager;
 use tokio::net::TcpListener;
+use tokio_util::sync::CancellationToken;
 use uuid::Uuid;
 
+use config::ServerConfig;
 use download::DownloadManager;
 use extensions::ExtensionManager;
 use history::HistoryStore;
+use qr::generate_qr_data_url;
 use routes::build_router;
 use screenshare::ScreenShareService;
 use state::ApiState;
 use twitch::TwitchService;
-use types::ServerInfo;
+use types::{BindScope, ServerInfo};
 
 use error::AppResult;
 
 pub const SERVER_PORT: u16 = 23455;
 #[cfg(not(debug_assertions))]
 pub const SERVER_HTTPS_PORT: u16 = 23456;
+/// How long in-flight requests get to finish once quit is requested.
+pub const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+/// How long the final history save may take before quit goes ahead anyway,
+/// e.g. when the data dir sits on an unresponsive network share. Saves go
+/// through a temp file, so a write cut short leaves the old history intact.
+pub const FLUSH_GRACE: Duration = Duration::from_secs(2);
 
 pub struct AppState {
     pub server_info: ServerInfo,
     pub api_state: ApiState,
+    /// Portal static files resolved at startup (always `None` in dev).
+    pub portal_dist: Option<PathBuf>,
+    /// Cancelled on quit; the listeners stop accepting and drain.
+    shutdown: CancellationToken,
+    /// Cancelled by `start_server` once the HTTP listener has stopped.
+    server_stopped: CancellationToken,
 }
 
 impl AppState {
-    pub fn new(app_data_dir: PathBuf) -> AppResult<Self> {
-        let history = Arc::new(HistoryStore::load(app_data_dir.clone())?);
-        let twitch = Arc::new(TwitchService::new());
+    pub fn new(app_data_dir: PathBuf, portal_dist: Option<PathBuf>) -> AppResult<Self> {
+        let portal_available = portal::self_test(portal_dist.as_deref());
+
+        let config = Arc::new(ServerConfig::from_env());
+        let history = if config.ephemeral {
+            eprintln!("[NoSubVOD] Ephemeral mode: nothing will be read from or written to disk");
+            Arc::new(HistoryStore::in_memory(app_data_dir.clone()))
+        } else {
+            Arc::new(HistoryStore::load(app_data_dir.clone())?)
+        };
+        if !config.extra_proxy_hosts.is_empty() {
+            eprintln!(
+                "[NoSubVOD] WARNING: the stream proxy will also fetch from {} (NOSUBVOD_EXTRA_PROXY_HOSTS); only list hosts you trust",
+                config.extra_proxy_hosts.join(", ")
+            );
+        }
+        let twitch = Arc::new(
+            TwitchService::new()
+                .with_extra_proxy_hosts(config.extra_proxy_hosts.clone())
+                .with_trending_options(config.trending),
+        );
         let download = Arc::new(DownloadManager::new());
         let screenshare = Arc::new(ScreenShareService::new());
         let extensions = Arc::new(ExtensionManager::new(app_data_dir));
@@ -71,7 +109,19 @@ impl AppState {
             }
         });
 
-        let ip = get_local_ipv4();
+        // `new` runs in the Tauri setup hook, outside the async runtime.
+        let settings = tauri::async_runtime::block_on(history.get_settings());
+        let bind_scope = settings.bind_scope;
+        let candidate_ips = match bind_scope {
+            BindScope::Lan => network::local_candidate_ips(config.ipv6),
+            BindScope::Localhost => {
+                eprintln!(
+                    "[NoSubVOD] Localhost mode: the portal is not reachable from other devices"
+                );
+                vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]
+            }
+        };
+        let ip = candidate_ips[0].to_string();
         let port = SERVER_PORT;
         // In dev mode the portal is served by Vite (port 5173) which proxies
         // /api calls to Axum. In release, Axum serves the portal directly.
@@ -88,14 +138,26 @@ impl AppState {
         // Generate a per-session authentication token to protect API endpoints
         let server_token = Uuid::new_v4().to_string().replace('-', "");
 
-        let url = format!("{portal_scheme}://{ip}:{portal_port}?t={server_token}");
-        let qrcode = generate_qr_data_url(&url);
+        let candidate_urls: Vec<String> = candidate_ips
+            .iter()
+            .map(|ip| network::portal_url(portal_scheme, *ip, portal_port, &server_token))
+            .collect();
+        let url = candidate_urls[0].clone();
+        // No other device can open a loopback URL, so there is nothing to scan.
+        let qrcode = match bind_scope {
+            BindScope::Lan => generate_qr_data_url(&url, &settings.qr),
+            BindScope::Localhost => String::new(),
+        };
+        let portal_url = url.clone();
 
         let server_info = ServerInfo {
             ip,
             port,
             url,
+            candidate_urls,
             qrcode,
+            portal_available,
+            bind_scope,
         };
 
         let oauth = Arc::new(auth::OAuthStateStore::new());
@@ -105,6 +167,7 @@ impl AppState {
             .max_capacity(1)
             .build();
 
+        let shutdown = CancellationToken::new();
         let api_state = ApiState {
             twitch,
             history,
@@ -112,73 +175,74 @@ impl AppState {
             screenshare,
             extensions,
             oauth,
+            config,
             server_token,
+            portal_url,
+            bind_scope,
             app_handle: None,
             download_cache,
+            portal_available,
+            shutdown: shutdown.clone(),
         };
 
         Ok(Self {
             server_info,
             api_state,
+            portal_dist,
+            shutdown,
+            server_stopped: CancellationToken::new(),
         })
     }
-}
-
-fn get_local_ipv4() -> String {
-    local_ip_address::local_ip()
-        .ok()
-        .and_then(|ip| match ip {
-            std::net::IpAddr::V4(v4) => Some(v4.to_string()),
-            _ => None,
-        })
-        .unwrap_or_else(|| "127.0.0.1".to_string())
-}
-
-fn generate_qr_data_url(data: &str) -> String {
-    let Ok(code) = QrCode::new(data.as_bytes()) else {
-        return String::new();
-    };
 
-    let image = code
-        .render::<image::Luma<u8>>()
-        .quiet_zone(true)
-        .max_dimensions(400, 400)
-        .build();
-
-    let mut buffer: Vec<u8> = Vec::new();
-    let encoder = image::codecs::png::PngEncoder::new(&mut buffer);
-    if encoder
-        .write_image(
-            image.as_raw(),
-            image.width(),
-            image.height(),
-            image::ExtendedColorType::L8,
-        )
-        .is_err()
-    {
-        return String::new();
+    /// Stops the servers (waiting up to `SHUTDOWN_GRACE` for in-flight
+    /// requests) and flushes history to disk (up to `FLUSH_GRACE`). Called
+    /// before `app.exit`.
+    pub async fn shutdown(&self) {
+        self.shutdown.cancel();
+        if tokio::time::timeout(SHUTDOWN_GRACE, self.server_stopped.cancelled())
+            .await
+            .is_err()
+        {
+            eprintln!("[NoSubVOD] Server did not stop within {SHUTDOWN_GRACE:?}");
+        }
+        match tokio::time::timeout(FLUSH_GRACE, self.api_state.history.flush()).await {
+            Ok(Ok(())) => {}
+            Ok(Err(e)) => eprintln!("[NoSubVOD] Failed to save history on quit: {e}"),
+            Err(_) => eprintln!("[NoSubVOD] History save did not finish within {FLUSH_GRACE:?}"),
+        }
     }
-
-    format!("data:image/png;base64,{}", B64.encode(&buffer))
 }
 
 pub async fn start_server(state: Arc<AppState>, app: AppHandle) {
-    // Resolve portal dist directory in release (bundled resources first).
-    let portal_dist = resolve_portal_dist(&app);
+    let portal_dist = state.portal_dist.clone();
 
     let mut api_state = state.api_state.clone();
     api_state.app_handle = Some(app.clone());
 
-    let router = build_router(api_state, portal_dist.clone());
-    let http_addr = std::net::SocketAddr::from(([0, 0, 0, 0], SERVER_PORT));
+    let ipv6 = api_state.config.ipv6;
+    let scope = state.server_info.bind_scope;
+    let router = build_router(api_state, portal_dist);
+    let http_addr = network::bind_addr(SERVER_PORT, ipv6, scope);
+    let shutdown = state.shutdown.clone();
+    // Also dropped on the early returns, so a failed bind never stalls quit.
+    let _stopped = state.server_stopped.clone().drop_guard();
 
     #[cfg(not(debug_assertions))]
     {
         let https_router = router.clone();
-        match ensure_or_create_tls_files(&app, &state.server_info.ip) {
+        match ensure_or_create_tls_files(&app, &state.server_info.candidate_urls) {
             Ok((cert_path, key_path)) => {
+                let https_shutdown = shutdown.clone();
                 tauri::async_runtime::spawn(async move {
-                    start_https_server(https_router, cert_path, key_path).await;
+                    start_https_server(
+                        https_router,
+                        cert_path,
+                        key_path,
+                        ipv6,
+                        scope,
+                        https_shutdown,
+                    )
+                    .await;
                 });
             }
             Err(e) => {
@@ -187,17 +251,17 @@ pub async fn start_server(state: Arc<AppState>, app: AppHandle) {
         }
     }
 
-    match TcpListener::bind(http_addr).await {
+    match network::bind_listener(http_addr).and_then(TcpListener::from_std) {
         Ok(listener) => {
             eprintln!("[NoSubVOD] HTTP server listening on {http_addr}");
-            #[cfg(not(debug_assertions))]
-            match &portal_dist {
-                Some(path) => eprintln!("[NoSubVOD] Serving portal from {}", path.display()),
-                None => eprintln!("[NoSubVOD] Portal static files not found in bundle resources"),
-            }
-            if let Err(e) = axum::serve(listener, router).await {
+            let service = router.into_make_service_with_connect_info::<std::net::SocketAddr>();
+            if let Err(e) = axum::serve(listener, service)
+                .with_graceful_shutdown(async move { shutdown.cancelled().await })
+                .await
+            {
                 eprintln!("[NoSubVOD] Server error: {e}");
             }
+            eprintln!("[NoSubVOD] HTTP server stopped");
         }
         Err(e) => {
             eprintln!("[NoSubVOD] Failed to bind port {SERVER_PORT}: {e}");
@@ -206,7 +270,10 @@ pub async fn start_server(state: Arc<AppState>, app: AppHandle) {
 }
 
 #[cfg(not(debug_assertions))]
-fn ensure_or_create_tls_files(app: &AppHandle, ip: &str) -> Result<(PathBuf, PathBuf), String> {
+fn ensure_or_create_tls_files(
+    app: &AppHandle,
+    candidate_urls: &[String],
+) -> Result<(PathBuf, PathBuf), String> {
     let app_data_dir = app
         .path()
         .app_data_dir()
@@ -220,8 +287,17 @@ fn ensure_or_create_tls_files(app: &AppHandle, ip: &str) -> Result<(PathBuf, Pat
     let key_path = tls_dir.join("portal-key.pem");
 
     let mut subject_alt_names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
-    if ip != "127.0.0.1" {
-        subject_alt_names.push(ip.to_string());
+    for host in candidate_urls
+        .iter()
+        .filter_map(|url| reqwest::Url::parse(url).ok())
+        .filter_map(|url| {
+            url.host_str()
+                .map(|h| h.trim_matches(['[', ']']).to_string())
+        })
+    {
+        if !subject_alt_names.contains(&host) {
+            subject_alt_names.push(host);
+        }
     }
 
     let certified = generate_simple_self_signed(subject_alt_names)
@@ -238,8 +314,15 @@ fn ensure_or_create_tls_files(app: &AppHandle, ip: &str) -> Result<(PathBuf, Pat
 }
 
 #[cfg(not(debug_assertions))]
-async fn start_https_server(router: axum::Router, cert_path: PathBuf, key_path: PathBuf) {
-    let https_addr = std::net::SocketAddr::from(([0, 0, 0, 0], SERVER_HTTPS_PORT));
+async fn start_https_server(
+    router: axum::Router,
+    cert_path: PathBuf,
+    key_path: PathBuf,
+    ipv6: bool,
+    scope: BindScope,
+    shutdown: CancellationToken,
+) {
+    let https_addr = network::bind_addr(SERVER_HTTPS_PORT, ipv6, scope);
 
     let cert_path_for_log = cert_path.clone();
     let key_path_for_log = key_path.clone();
@@ -256,43 +339,27 @@ async fn start_https_server(router: axum::Router, cert_path: PathBuf, key_path:
         }
     };
 
+    let listener = match network::bind_listener(https_addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            eprintln!("[NoSubVOD] Failed to bind HTTPS port {SERVER_HTTPS_PORT}: {e}");
+            return;
+        }
+    };
+
+    let handle = axum_server::Handle::new();
+    let shutdown_handle = handle.clone();
+    tauri::async_runtime::spawn(async move {
+        shutdown.cancelled().await;
+        shutdown_handle.graceful_shutdown(Some(SHUTDOWN_GRACE));
+    });
+
     eprintln!("[NoSubVOD] HTTPS server listening on {https_addr}");
-    if let Err(e) = axum_server::bind_rustls(https_addr, config)
-        .serve(router.into_make_service())
+    if let Err(e) = axum_server::from_tcp_rustls(listener, config)
+        .handle(handle)
+        .serve(router.into_make_service_with_connect_info::<std::net::SocketAddr>())
         .await
     {
         eprintln!("[NoSubVOD] HTTPS server error: {e}");
     }
 }
-
-fn resolve_portal_dist(_app: &AppHandle) -> Option<PathBuf> {
-    #[cfg(debug_assertions)]
-    {
-        None
-    }
-    #[cfg(not(debug_assertions))]
-    {
-        let mut candidates: Vec<PathBuf> = Vec::new();
-
-        if let Ok(resource_dir) = _app.path().resource_dir() {
-            candidates.push(resource_dir.join("portal"));
-            candidates.push(resource_dir.join("dist").join("portal"));
-            candidates.push(resource_dir.join("_up_").join("portal"));
-            candidates.push(resource_dir.join("_up_").join("dist").join("portal"));
-        }
-
-        if let Ok(exe) = std::env::current_exe() {
-            if let Some(exe_dir) = exe.parent() {
-                candidates.push(exe_dir.join("portal"));
-                candidates.push(exe_dir.join("resources").join("portal"));
-                candidates.push(exe_dir.join("resources").join("dist").join("portal"));
-                candidates.push(exe_dir.join("_up_").join("portal"));
-                candidates.push(exe_dir.join("_up_").join("dist").join("portal"));
-            }
-        }
-
-        candidates
-            .into_iter()
-            .find(|path| path.join("index.html").exists())
-    }
-}
@@ -0,0 +1,202 @@
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use socket2::{Domain, Protocol, Socket, Type};
+
+use super::types::BindScope;
+
+// ── Address advertisement ──────────────────────────────────────────────────────
+
+/// `host:port` for a URL authority; IPv6 literals are bracketed.
+pub fn format_authority(ip: IpAddr, port: u16) -> String {
+    match ip {
+        IpAddr::V4(v4) => format!("{v4}:{port}"),
+        IpAddr::V6(v6) => format!("[{v6}]:{port}"),
+    }
+}
+
+pub fn portal_url(scheme: &str, ip: IpAddr, port: u16, token: &str) -> String {
+    format!("{scheme}://{}?t={token}", format_authority(ip, port))
+}
+
+/// Whether a phone on the same network could reach this address. Link-local
+/// IPv6 is skipped: it needs a zone id (`%eth0`) that browsers don't accept.
+fn is_advertisable(ip: &IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => !v4.is_loopback() && !v4.is_unspecified() && !v4.is_link_local(),
+        IpAddr::V6(v6) => {
+            let link_local = (v6.segments()[0] & 0xffc0) == 0xfe80;
+            !v6.is_loopback() && !v6.is_unspecified() && !v6.is_multicast() && !link_local
+        }
+    }
+}
+
+/// Ranks candidates: private IPv4 first (typical home LAN), then other IPv4,
+/// then unique-local IPv6 (`fd00::/8`), then global IPv6.
+fn rank(ip: &IpAddr) -> u8 {
+    match ip {
+        IpAddr::V4(v4) if v4.is_private() => 0,
+        IpAddr::V4(_) => 1,
+        IpAddr::V6(v6) if (v6.segments()[0] & 0xfe00) == 0xfc00 => 2,
+        IpAddr::V6(_) => 3,
+    }
+}
+
+/// Orders and filters interface addresses into the advertised candidate list.
+pub fn select_candidate_ips(addrs: impl IntoIterator<Item = IpAddr>, ipv6: bool) -> Vec<IpAddr> {
+    let mut ips: Vec<IpAddr> = Vec::new();
+    for ip in addrs {
+        if is_advertisable(&ip) && (ipv6 || ip.is_ipv4()) && !ips.contains(&ip) {
+            ips.push(ip);
+        }
+    }
+    ips.sort_by_key(rank);
+    ips
+}
+
+/// Reachable local addresses, best first. Falls back to loopback so the
+/// desktop window always has something to show.
+pub fn local_candidate_ips(ipv6: bool) -> Vec<IpAddr> {
+    let mut addrs: Vec<IpAddr> = local_ip_address::local_ip().into_iter().collect();
+    if let Ok(interfaces) = local_ip_address::list_afinet_netifas() {
+        addrs.extend(interfaces.into_iter().map(|(_, ip)| ip));
+    }
+
+    let ips = select_candidate_ips(addrs, ipv6);
+    if ips.is_empty() {
+        vec![IpAddr::V4(Ipv4Addr::LOCALHOST)]
+    } else {
+        ips
+    }
+}
+
+/// Whether `client` looks like it is on the same LAN as one of `server_ips`:
+/// same /24 for IPv4, same /64 for IPv6. Loopback is the desktop itself.
+/// Only a heuristic; a /23 home network or routed VLANs can fool it.
+pub fn shares_subnet(client: IpAddr, server_ips: &[IpAddr]) -> bool {
+    let client = client.to_canonical();
+    if client.is_loopback() {
+        return true;
+    }
+    server_ips
+        .iter()
+        .any(|server| match (client, server.to_canonical()) {
+            (IpAddr::V4(c), IpAddr::V4(s)) => c.octets()[..3] == s.octets()[..3],
+            (IpAddr::V6(c), IpAddr::V6(s)) => c.segments()[..4] == s.segments()[..4],
+            _ => false,
+        })
+}
+
+// ── Listener binding ───────────────────────────────────────────────────────────
+
+/// `[::]` or `0.0.0.0`; `127.0.0.1` in localhost mode whatever `ipv6` says,
+/// since browsers on the same machine resolve `localhost` to it too.
+pub fn bind_addr(port: u16, ipv6: bool, scope: BindScope) -> SocketAddr {
+    if scope == BindScope::Localhost {
+        SocketAddr::from((Ipv4Addr::LOCALHOST, port))
+    } else if ipv6 {
+        SocketAddr::from((Ipv6Addr::UNSPECIFIED, port))
+    } else {
+        SocketAddr::from((Ipv4Addr::UNSPECIFIED, port))
+    }
+}
+
+/// Binds a non-blocking listener. On `[::]` it clears `IPV6_V6ONLY` so IPv4
+/// clients keep working (Windows defaults to v6-only); if the platform refuses
+/// that, the socket stays v6-only rather than failing to start.
+pub fn bind_listener(addr: SocketAddr) -> std::io::Result<std::net::TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        if let Err(e) = socket.set_only_v6(false) {
+            eprintln!("[NoSubVOD] Dual-stack unavailable, serving IPv6 only: {e}");
+        }
+    }
+    // Lets a restart rebind past TIME_WAIT. On Windows SO_REUSEADDR would
+    // instead let a second instance share the port silently.
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    socket.set_nonblocking(true)?;
+    Ok(socket.into())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ipv6_urls_are_bracketed() {
+        let v6: IpAddr = "2001:db8::42".parse().unwrap();
+        assert_eq!(
+            portal_url("https", v6, 23456, "tok"),
+            "https://[2001:db8::42]:23456?t=tok"
+        );
+        let v4: IpAddr = "192.168.1.20".parse().unwrap();
+        assert_eq!(
+            portal_url("https", v4, 23456, "tok"),
+            "https://192.168.1.20:23456?t=tok"
+        );
+    }
+
+    #[test]
+    fn candidates_prefer_private_ipv4_and_gate_ipv6() {
+        let addrs: Vec<IpAddr> = [
+            "2001:db8::42",
+            "fe80::1",
+            "::1",
+            "127.0.0.1",
+            "fd12:3456::7",
+            "192.168.1.20",
+            "192.168.1.20",
+        ]
+        .iter()
+        .map(|s| s.parse().unwrap())
+        .collect();
+
+        let v4_only = select_candidate_ips(addrs.clone(), false);
+        assert_eq!(v4_only, vec!["192.168.1.20".parse::<IpAddr>().unwrap()]);
+
+        let dual: Vec<String> = select_candidate_ips(addrs, true)
+            .iter()
+            .map(|ip| ip.to_string())
+            .collect();
+        assert_eq!(dual, vec!["192.168.1.20", "fd12:3456::7", "2001:db8::42"]);
+    }
+
+    #[test]
+    fn subnet_check_compares_slash_24_and_slash_64() {
+        let servers: Vec<IpAddr> = ["192.168.1.20", "fd12:3456:0:1::7"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let same = |raw: &str| shares_subnet(raw.parse().unwrap(), &servers);
+
+        assert!(same("192.168.1.87"));
+        assert!(same("::ffff:192.168.1.87"));
+        assert!(same("fd12:3456:0:1::abcd"));
+        assert!(same("127.0.0.1"));
+        assert!(!same("192.168.2.87"));
+        assert!(!same("10.0.0.5"));
+        assert!(!same("fd12:3456:0:2::abcd"));
+    }
+
+    #[test]
+    fn localhost_scope_binds_loopback_only() {
+        assert_eq!(
+            bind_addr(23455, true, BindScope::Localhost),
+            "127.0.0.1:23455".parse::<SocketAddr>().unwrap()
+        );
+        assert!(bind_addr(23455, false, BindScope::Lan)
+            .ip()
+            .is_unspecified());
+    }
+
+    #[test]
+    fn dual_stack_listener_binds_unspecified_v6() {
+        let Ok(listener) = bind_listener(bind_addr(0, true, BindScope::Lan)) else {
+            // Hosts without IPv6 (some CI sandboxes) can't run this check.
+            return;
+        };
+        assert!(listener.local_addr().unwrap().is_ipv6());
+    }
+}
@@ -0,0 +1,136 @@
+use once_cell::sync::Lazy;
+use regex::Regex;
+
+use super::types::SubEntry;
+use super::url_utils::{resolve_twitch_url, TwitchUrlKind};
+use super::validation::is_valid_login;
+
+// ── OPML sub lists ─────────────────────────────────────────────────────────────
+
+static RE_OUTLINE: Lazy<Regex> = Lazy::new(|| Regex::new(r"(?is)<outline\b([^>]*)>").unwrap());
+static RE_ATTRIBUTE: Lazy<Regex> =
+    Lazy::new(|| Regex::new(r#"(?s)([A-Za-z_:][\w:.-]*)\s*=\s*(?:"([^"]*)"|'([^']*)')"#).unwrap());
+
+fn escape(raw: &str) -> String {
+    raw.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+fn unescape(raw: &str) -> String {
+    raw.replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// The sub list as an OPML 2.0 document: one `link` outline per channel,
+/// with the login as `text` and the channel page as `url` / `htmlUrl`.
+pub fn subs_to_opml(subs: &[SubEntry]) -> String {
+    let mut doc = String::from(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<opml version=\"2.0\">\n  <head>\n    <title>NoSubVOD subs</title>\n  </head>\n  <body>\n",
+    );
+    for sub in subs {
+        let login = escape(&sub.login);
+        let title = escape(if sub.display_name.is_empty() {
+            &sub.login
+        } else {
+            &sub.display_name
+        });
+        doc.push_str(&format!(
+            "    <outline type=\"link\" text=\"{login}\" title=\"{title}\" url=\"https://twitch.tv/{login}\" htmlUrl=\"https://twitch.tv/{login}\"/>\n"
+        ));
+    }
+    doc.push_str("  </body>\n</opml>\n");
+    doc
+}
+
+/// Channel logins named by the outlines of an OPML document, in order and
+/// deduplicated. An outline with a link counts only if it is a Twitch
+/// channel URL (`url`, `htmlUrl` or `xmlUrl`); a link-less leaf outline
+/// counts when its `text` is a valid login. Folders are skipped.
+pub fn logins_from_opml(raw: &str) -> Vec<String> {
+    let mut logins: Vec<String> = Vec::new();
+    for outline in RE_OUTLINE.captures_iter(raw) {
+        let is_leaf = outline[1].trim_end().ends_with('/');
+        let mut has_url = false;
+        let mut url_login = None;
+        let mut text_login = None;
+        for attr in RE_ATTRIBUTE.captures_iter(&outline[1]) {
+            let value = unescape(
+                attr.get(2)
+                    .or_else(|| attr.get(3))
+                    .map_or("", |m| m.as_str()),
+            );
+            match attr[1].to_ascii_lowercase().as_str() {
+                "url" | "htmlurl" | "xmlurl" => {
+                    has_url = true;
+                    if url_login.is_none() {
+                        url_login = resolve_twitch_url(&value)
+                            .filter(|r| r.kind == TwitchUrlKind::Channel)
+                            .map(|r| r.id_or_login);
+                    }
+                }
+                "text" => {
+                    let login = value.trim().trim_start_matches('@').to_lowercase();
+                    text_login = is_valid_login(&login).then_some(login);
+                }
+                _ => {}
+            }
+        }
+        let login = if has_url {
+            url_login
+        } else {
+            text_login.filter(|_| is_leaf)
+        };
+        if let Some(login) = login {
+            if !logins.contains(&login) {
+                logins.push(login);
+            }
+        }
+    }
+    logins
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn opml_export_round_trips_through_import() {
+        let subs = vec![
+            SubEntry {
+                login: "alpha".to_string(),
+                display_name: "Alpha & Co".to_string(),
+                profile_image_url: String::new(),
+                modified_at: 0,
+            },
+            SubEntry {
+                login: "bravo_2".to_string(),
+                display_name: String::new(),
+                profile_image_url: String::new(),
+                modified_at: 0,
+            },
+        ];
+        let opml = subs_to_opml(&subs);
+        assert!(opml.contains(r#"title="Alpha &amp; Co""#));
+        assert_eq!(logins_from_opml(&opml), ["alpha", "bravo_2"]);
+    }
+
+    #[test]
+    fn opml_import_reads_urls_then_text() {
+        let opml = r#"<opml version="1.0"><body>
+            <outline text="Streams">
+              <outline title="Charlie" htmlUrl='https://www.twitch.tv/Charlie/videos'/>
+              <outline text="delta"/>
+              <outline text="not a login" url="https://example.com/feed"/>
+              <outline text="charlie"/>
+              <outline text="Directory" url="https://twitch.tv/directory"/>
+            </outline>
+        </body></opml>"#;
+        assert_eq!(logins_from_opml(opml), ["charlie", "delta"]);
+    }
+}
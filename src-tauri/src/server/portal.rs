@@ -0,0 +1,241 @@
+use std::path::{Path, PathBuf};
+
+#[cfg(debug_assertions)]
+use axum::response::Redirect;
+use axum::{
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use tauri::AppHandle;
+#[cfg(not(debug_assertions))]
+use tauri::Manager;
+use tower_http::services::{ServeDir, ServeFile};
+
+// ── Portal asset resolution ────────────────────────────────────────────────────
+
+/// Env var pointing at a custom portal build; wins over the bundled
+/// candidates when it holds an `index.html`.
+const PORTAL_DIR_ENV: &str = "NOSUBVOD_PORTAL_DIR";
+
+pub fn resolve_portal_dist(_app: &AppHandle) -> Option<PathBuf> {
+    let override_dir = std::env::var_os(PORTAL_DIR_ENV).map(PathBuf::from);
+
+    #[cfg(debug_assertions)]
+    {
+        pick_portal_dist(override_dir, Vec::new())
+    }
+    #[cfg(not(debug_assertions))]
+    {
+        let mut candidates: Vec<PathBuf> = Vec::new();
+
+        if let Ok(resource_dir) = _app.path().resource_dir() {
+            candidates.push(resource_dir.join("portal"));
+            candidates.push(resource_dir.join("dist").join("portal"));
+            candidates.push(resource_dir.join("_up_").join("portal"));
+            candidates.push(resource_dir.join("_up_").join("dist").join("portal"));
+        }
+
+        if let Ok(exe) = std::env::current_exe() {
+            if let Some(exe_dir) = exe.parent() {
+                candidates.push(exe_dir.join("portal"));
+                candidates.push(exe_dir.join("resources").join("portal"));
+                candidates.push(exe_dir.join("resources").join("dist").join("portal"));
+                candidates.push(exe_dir.join("_up_").join("portal"));
+                candidates.push(exe_dir.join("_up_").join("dist").join("portal"));
+            }
+        }
+
+        pick_portal_dist(override_dir, candidates)
+    }
+}
+
+fn pick_portal_dist(override_dir: Option<PathBuf>, candidates: Vec<PathBuf>) -> Option<PathBuf> {
+    if let Some(dir) = override_dir.filter(|dir| !dir.as_os_str().is_empty()) {
+        if dir.join("index.html").is_file() {
+            eprintln!(
+                "[NoSubVOD] Using portal dir from {PORTAL_DIR_ENV}: {}",
+                dir.display()
+            );
+            return Some(dir);
+        }
+        eprintln!(
+            "[NoSubVOD] Ignoring {PORTAL_DIR_ENV}={} (not a directory with an index.html)",
+            dir.display()
+        );
+    }
+
+    candidates
+        .into_iter()
+        .find(|path| path.join("index.html").exists())
+}
+
+/// Startup self-test: returns whether the portal can be served. In dev the
+/// portal is served by Vite, so the check only matters for release builds.
+pub fn self_test(portal_dist: Option<&Path>) -> bool {
+    if cfg!(debug_assertions) {
+        return true;
+    }
+
+    match portal_dist {
+        Some(path) if path.join("index.html").exists() => {
+            eprintln!("[NoSubVOD] Serving portal from {}", path.display());
+            true
+        }
+        _ => {
+            eprintln!("[NoSubVOD] ==================================================");
+            eprintln!("[NoSubVOD] ERROR: portal assets (index.html) were not found.");
+            eprintln!("[NoSubVOD] The web portal will only show a diagnostic page.");
+            eprintln!("[NoSubVOD] Reinstall NoSubVOD or rebuild the portal bundle.");
+            eprintln!("[NoSubVOD] ==================================================");
+            false
+        }
+    }
+}
+
+// ── Router integration ─────────────────────────────────────────────────────────
+
+/// Mounts the portal on `/`: static files when available, the Vite redirect
+/// in dev, and a diagnostic page when release assets are missing.
+pub fn mount_portal(router: Router, portal_dist: Option<PathBuf>) -> Router {
+    mount_portal_with(router, portal_dist, cfg!(debug_assertions))
+}
+
+pub(crate) fn mount_portal_with(
+    router: Router,
+    portal_dist: Option<PathBuf>,
+    dev_mode: bool,
+) -> Router {
+    match portal_dist {
+        Some(portal_path) if portal_path.exists() => {
+            let serve_dir = ServeDir::new(&portal_path)
+                .append_index_html_on_directories(true)
+                .fallback(ServeFile::new(portal_path.join("index.html")));
+            router.nest_service("/", serve_dir)
+        }
+        #[cfg(debug_assertions)]
+        _ if dev_mode => router.fallback(get(handle_dev_portal_redirect)),
+        _ => {
+            let _ = dev_mode;
+            router.fallback(get(handle_portal_missing))
+        }
+    }
+}
+
+#[cfg(debug_assertions)]
+async fn handle_dev_portal_redirect(
+    headers: axum::http::HeaderMap,
+    uri: axum::http::Uri,
+) -> Redirect {
+    let host = headers
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or("localhost");
+
+    let host_without_port = host.split(':').next().unwrap_or("localhost");
+    let path_and_query = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
+
+    Redirect::temporary(&format!("https://{host_without_port}:5173{path_and_query}"))
+}
+
+const PORTAL_MISSING_HTML: &str = r#"<!doctype html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>NoSubVOD - portal assets missing</title>
+<style>
+body { font-family: system-ui, sans-serif; background: #0e0e10; color: #efeff1; margin: 0; padding: 2rem; }
+main { max-width: 40rem; margin: 0 auto; }
+h1 { color: #bf94ff; }
+code { background: #26262c; padding: 0.1rem 0.3rem; border-radius: 4px; }
+</style>
+</head>
+<body>
+<main>
+<h1>Portal assets missing</h1>
+<p>The NoSubVOD server is running, but the web portal files (<code>index.html</code>) could not be found next to the application.</p>
+<p>Reinstalling NoSubVOD usually fixes this. If you built it yourself, make sure the portal was built before bundling.</p>
+<p>The API is still reachable: <code>/api/health</code> reports <code>"portalAvailable": false</code>.</p>
+</main>
+</body>
+</html>
+"#;
+
+pub async fn handle_portal_missing() -> Response {
+    (
+        StatusCode::SERVICE_UNAVAILABLE,
+        [(header::CACHE_CONTROL, "no-store")],
+        Html(PORTAL_MISSING_HTML),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    #[tokio::test]
+    async fn missing_portal_in_release_serves_diagnostic_page() {
+        let app = mount_portal_with(Router::new(), None, false);
+
+        let response = app
+            .oneshot(Request::builder().uri("/").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert!(response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .unwrap()
+            .starts_with("text/html"));
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("Portal assets missing"));
+    }
+
+    #[tokio::test]
+    async fn missing_portal_path_on_disk_also_serves_diagnostic_page() {
+        let dir = tempfile::tempdir().unwrap();
+        let app = mount_portal_with(Router::new(), Some(dir.path().join("nope")), false);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/some/deep/link")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+
+    #[test]
+    fn portal_dir_override_wins_over_candidates_when_valid() {
+        let custom = tempfile::tempdir().unwrap();
+        let bundled = tempfile::tempdir().unwrap();
+        std::fs::write(custom.path().join("index.html"), "custom").unwrap();
+        std::fs::write(bundled.path().join("index.html"), "bundled").unwrap();
+        let candidates = vec![bundled.path().to_path_buf()];
+
+        assert_eq!(
+            pick_portal_dist(Some(custom.path().to_path_buf()), candidates.clone()),
+            Some(custom.path().to_path_buf())
+        );
+        // An override without an index.html falls back to the search.
+        assert_eq!(
+            pick_portal_dist(Some(custom.path().join("missing")), candidates.clone()),
+            Some(bundled.path().to_path_buf())
+        );
+        assert_eq!(
+            pick_portal_dist(None, candidates),
+            Some(bundled.path().to_path_buf())
+        );
+    }
+}
@@ -0,0 +1,88 @@
+use base64::engine::general_purpose::STANDARD as B64;
+use base64::Engine;
+use image::ImageEncoder;
+use qrcode::render::svg;
+use qrcode::{EcLevel, QrCode};
+
+use super::types::{QrErrorCorrection, QrPreferences};
+
+// ── Portal QR code rendering ───────────────────────────────────────────────────
+
+fn encode(data: &str, prefs: &QrPreferences) -> Option<QrCode> {
+    let ec_level = match prefs.error_correction {
+        QrErrorCorrection::L => EcLevel::L,
+        QrErrorCorrection::M => EcLevel::M,
+        QrErrorCorrection::Q => EcLevel::Q,
+        QrErrorCorrection::H => EcLevel::H,
+    };
+    QrCode::with_error_correction_level(data.as_bytes(), ec_level).ok()
+}
+
+/// PNG at least `prefs.size` pixels wide, with every module a whole number of
+/// pixels so phone cameras get crisp edges.
+pub fn generate_qr_png(data: &str, prefs: &QrPreferences) -> Option<Vec<u8>> {
+    let image = encode(data, prefs)?
+        .render::<image::Luma<u8>>()
+        .quiet_zone(true)
+        .min_dimensions(prefs.size, prefs.size)
+        .build();
+
+    let mut buffer: Vec<u8> = Vec::new();
+    image::codecs::png::PngEncoder::new(&mut buffer)
+        .write_image(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            image::ExtendedColorType::L8,
+        )
+        .ok()?;
+    Some(buffer)
+}
+
+/// Same code as [`generate_qr_png`] as a standalone SVG document, for
+/// scaling without blur and for print. Keeps the quiet zone.
+pub fn generate_qr_svg(data: &str, prefs: &QrPreferences) -> Option<String> {
+    Some(
+        encode(data, prefs)?
+            .render::<svg::Color>()
+            .quiet_zone(true)
+            .min_dimensions(prefs.size, prefs.size)
+            .dark_color(svg::Color("#000000"))
+            .light_color(svg::Color("#ffffff"))
+            .build(),
+    )
+}
+
+/// [`generate_qr_png`] as a `data:` URL; empty when rendering fails.
+pub fn generate_qr_data_url(data: &str, prefs: &QrPreferences) -> String {
+    generate_qr_png(data, prefs)
+        .map(|png| format!("data:image/png;base64,{}", B64.encode(png)))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn svg_and_png_share_size_and_quiet_zone() {
+        let prefs = QrPreferences {
+            size: 300,
+            ..QrPreferences::default()
+        };
+        let url = "https://192.168.1.10:23456/?t=0123456789abcdef";
+
+        let svg = generate_qr_svg(url, &prefs).unwrap();
+        assert!(svg.contains("<svg"));
+        // Light background covers the whole canvas, quiet zone included.
+        assert!(svg.contains(r##"fill="#ffffff""##));
+
+        let png = generate_qr_png(url, &prefs).unwrap();
+        let decoded = image::load_from_memory(&png).unwrap().to_luma8();
+        assert!(decoded.width() >= 300);
+        // Top-left corner sits in the quiet zone, so it is light.
+        assert_eq!(decoded.get_pixel(0, 0).0, [255]);
+
+        assert!(generate_qr_data_url(url, &prefs).starts_with("data:image/png;base64,"));
+    }
+}
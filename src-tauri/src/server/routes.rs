@@ -1,14 +1,16 @@
-#[cfg(debug_assertions)]
-use axum::response::Redirect;
 use axum::{
     body::Body,
-    extract::{ws::WebSocketUpgrade, Path, Query, State},
-    http::{header, StatusCode},
+    extract::{ws::WebSocketUpgrade, ConnectInfo, DefaultBodyLimit, Path, State},
+    http::{header, HeaderValue, StatusCode},
     middleware,
-    response::{IntoResponse, Response},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     routing::{delete, get, post, put},
     Json, Router,
 };
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use sysinfo::{CpuRefreshKind, MemoryRefreshKind, RefreshKind, System};
@@ -16,32 +18,58 @@ use tauri::Emitter;
 use tauri_plugin_autostart::ManagerExt;
 #[cfg(target_os = "windows")]
 use tokio::process::Command;
+use tokio::sync::broadcast::error::RecvError;
 use tower::ServiceExt;
-use tower_http::compression::CompressionLayer;
+use tower_http::compression::{
+    predicate::{NotForContentType, Predicate},
+    CompressionLayer, DefaultPredicate,
+};
 use tower_http::cors::CorsLayer;
-use tower_http::services::{ServeDir, ServeFile};
+use tower_http::services::ServeFile;
 use tower_http::trace::TraceLayer;
 
 use super::{
+    client_ip::client_ip,
     download_paths::{
         build_master_m3u8_url, build_output_file_base_path, build_output_file_path,
         resolve_download_output_dir,
     },
     dto::{
-        ChatQuery, ChatSendBody, DownloadRequest, DownloadedFile, HistoryBody, HistoryListQuery,
-        LiveCategoryQuery, LiveQuery, LiveSearchQuery, LiveStatusQuery, PagedQuery,
-        SearchCategoryQuery, SearchQuery, SettingsPatch, TrustedDevicePatch, VariantProxyQuery,
+        BlocklistBody, ChatQuery, ChatRangeQuery, ChatSendBody, DownloadRequest, DownloadedFile,
+        HistoryBody, HistoryListQuery, LiveCategoryQuery, LiveQuery, LiveSearchQuery,
+        LiveStatusQuery, PagedQuery, QrQuery, ResolveUrlBody, SearchCategoryQuery, SearchQuery,
+        SettingsPatch, SubImportBody, SubsExportQuery, SyncChangesQuery, ThumbQuery, TrendsQuery,
+        TrustedDevicePatch, VariantProxyQuery, VodBatchBody,
+    },
+    error::{handle_api_not_found, AppError, AppResult},
+    history::{now_ms, StoreEvent},
+    middleware::{
+        auth_middleware, basic_auth_middleware, request_timeout_middleware,
+        response_time_middleware, security_headers_middleware, BasicAuthGate,
     },
-    error::{AppError, AppResult},
-    middleware::{auth_middleware, security_headers_middleware},
+    network, opml, portal,
+    qr::{generate_qr_png, generate_qr_svg},
     screenshare::StartScreenShareRequest,
     state::ApiState,
-    types::{SubEntry, WatchlistEntry},
+    twitch::{
+        continue_watching, is_partially_watched, live_subs, partition_subs_by_live,
+        summarize_history, CategoryRef, CONTINUE_WATCHING_MAX, SUBS_RECENT_VODS_DEFAULT_LIMIT,
+        SUBS_RECENT_VODS_MAX_LIMIT, TOP_CHANNELS_MAX, TRENDING_DEFAULT_LIMIT, TRENDING_MAX_LIMIT,
+        VIEWER_TREND_MAX_SAMPLES, VOD_BATCH_MAX_IDS,
+    },
+    types::{
+        BindScope, Blocklist, HistoryEntry, HistoryWriteResponse, LiveSort, LiveStatusReport,
+        SubEntry, TrendsExclusions, Vod, WatchlistEntry, QR_MAX_SIZE, QR_MIN_SIZE,
+    },
+    url_utils::{resolve_twitch_url, segment_content_type},
     validation::{
         filter_hevc_variants_for_ios, is_ios_family_request, is_valid_id, is_valid_login,
+        parse_language_list, parse_thumb_size, parse_timecode, parse_vod_id, ApiJson, ApiQuery,
+        VodId,
     },
 };
 use moka::future::Cache;
+use std::net::SocketAddr;
 use std::time::Duration;
 
 async fn handle_get_extensions(State(state): State<ApiState>) -> impl IntoResponse {
@@ -71,7 +99,7 @@ async fn handle_extension_files(
 
     match ServeFile::new(&full_path).oneshot(req).await {
         Ok(res) => Ok(res.into_response()),
-        Err(_) => Err(AppError::NotFound("File not found".to_string())),
+        Err(_) => Err(AppError::NotFound("File not found".to_string()).with_code("FILE_NOT_FOUND")),
     }
 }
 
@@ -81,7 +109,7 @@ async fn handle_get_screenshare_state(State(state): State<ApiState>) -> impl Int
 
 async fn handle_start_screenshare(
     State(state): State<ApiState>,
-    Json(request): Json<StartScreenShareRequest>,
+    ApiJson(request): ApiJson<StartScreenShareRequest>,
 ) -> AppResult<Response> {
     let session = state
         .screenshare
@@ -216,14 +244,10 @@ fn m3u8_response(body: String) -> Response {
 // ── Route handlers ────────────────────────────────────────────────────────────
 
 async fn handle_vod_chat(
-    Path(vod_id): Path<String>,
-    Query(q): Query<ChatQuery>,
+    VodId(vod_id): VodId,
+    ApiQuery(q): ApiQuery<ChatQuery>,
     State(state): State<ApiState>,
 ) -> AppResult<Response> {
-    if !is_valid_id(&vod_id) {
-        return Err(AppError::BadRequest("Invalid VOD ID".to_string()));
-    }
-
     if let Some(keyword) = q.keyword {
         if !keyword.trim().is_empty() {
             let data = state
@@ -239,49 +263,163 @@ async fn handle_vod_chat(
     Ok(Json(data).into_response())
 }
 
-async fn handle_vod_markers(
-    Path(vod_id): Path<String>,
+/// Longest window `/chat/range` will page through in one request.
+const CHAT_RANGE_MAX_SECS: f64 = 3600.0;
+
+async fn handle_vod_chat_range(
+    VodId(vod_id): VodId,
+    ApiQuery(q): ApiQuery<ChatRangeQuery>,
     State(state): State<ApiState>,
 ) -> AppResult<Response> {
-    if !is_valid_id(&vod_id) {
-        return Err(AppError::BadRequest("Invalid VOD ID".to_string()));
+    let (Some(start), Some(end)) = (q.start, q.end) else {
+        return Err(AppError::BadRequest(
+            "Missing start or end parameter".to_string(),
+        ));
+    };
+    if !start.is_finite() || !end.is_finite() || start < 0.0 || start > end {
+        return Err(AppError::BadRequest(
+            "start and end must satisfy 0 <= start <= end".to_string(),
+        ));
     }
+    if end - start > CHAT_RANGE_MAX_SECS {
+        return Err(AppError::BadRequest(format!(
+            "Chat range is limited to {CHAT_RANGE_MAX_SECS} seconds"
+        )));
+    }
+
+    let data = state
+        .twitch
+        .fetch_video_chat_range(&vod_id, start, end)
+        .await?;
+    Ok(Json(data).into_response())
+}
+
+async fn handle_vod_markers(
+    VodId(vod_id): VodId,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
     let data = state.twitch.fetch_video_markers(&vod_id).await?;
     Ok(Json(data).into_response())
 }
 
-async fn handle_vod_info(
-    Path(vod_id): Path<String>,
+/// Metadata for many VODs in one request, keyed by id; ids that don't
+/// resolve are left out, and a failed Twitch lookup is an error rather than
+/// a short map. Saves the portal one request per VOD when rebuilding a
+/// shared playlist or an imported watchlist.
+async fn handle_vods_batch(
     State(state): State<ApiState>,
+    ApiJson(body): ApiJson<VodBatchBody>,
 ) -> AppResult<Response> {
-    if !is_valid_id(&vod_id) {
-        return Err(AppError::BadRequest("Invalid VOD ID".to_string()));
+    if body.ids.len() > VOD_BATCH_MAX_IDS {
+        return Err(AppError::BadRequest(format!(
+            "At most {VOD_BATCH_MAX_IDS} ids per batch"
+        )));
+    }
+    let mut ids: Vec<String> = Vec::with_capacity(body.ids.len());
+    for raw in &body.ids {
+        let id = parse_vod_id(raw)?;
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
     }
+    Ok(Json(state.twitch.fetch_vods_map(&ids).await?).into_response())
+}
+
+async fn handle_vod_chapters(
+    VodId(vod_id): VodId,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    let chapters = state.twitch.fetch_vod_chapters(&vod_id).await?;
+    Ok(Json(chapters).into_response())
+}
+
+/// Portal QR code rendered on demand, e.g. as SVG for print or hi-dpi.
+async fn handle_get_qr(
+    ApiQuery(q): ApiQuery<QrQuery>,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    if state.bind_scope == BindScope::Localhost {
+        return Err(AppError::NotFound(
+            "No portal QR code in localhost mode".to_string(),
+        ));
+    }
+    let mut prefs = state.history.get_settings().await.qr;
+    if let Some(size) = q.size {
+        prefs.size = size.clamp(QR_MIN_SIZE, QR_MAX_SIZE);
+    }
+
+    let (content_type, body) = match q.format.as_deref().unwrap_or("png") {
+        "png" => (
+            "image/png",
+            generate_qr_png(&state.portal_url, &prefs).map(Body::from),
+        ),
+        "svg" => (
+            "image/svg+xml",
+            generate_qr_svg(&state.portal_url, &prefs).map(Body::from),
+        ),
+        _ => {
+            return Err(AppError::BadRequest(
+                "format must be png or svg".to_string(),
+            ))
+        }
+    };
+    let body = body.ok_or_else(|| AppError::Internal("Failed to render QR code".to_string()))?;
+
+    Ok(Response::builder()
+        .header(header::CONTENT_TYPE, content_type)
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(body)
+        .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response()))
+}
+
+async fn handle_vod_info(
+    VodId(vod_id): VodId,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
     let vods = state.twitch.fetch_vods_by_ids(vec![vod_id]).await;
     if let Some(vod) = vods.into_iter().next() {
         Ok(Json(vod).into_response())
     } else {
-        Err(AppError::NotFound("VOD not found".to_string()))
+        Err(AppError::NotFound("VOD not found".to_string()).with_code("VOD_NOT_FOUND"))
     }
 }
 
+/// Header carrying the `?t=` start offset (in seconds) of a shared link, so
+/// the player can seek on load. Stored history is still read from
+/// `/api/history/:vod_id`; the offset is only echoed, never recorded.
+const START_OFFSET_HEADER: &str = "x-start-offset";
+
+/// `t` is also the auth token parameter, so only a value that parses as a
+/// timecode (`600`, `1h2m3s`) is taken as the start offset.
+fn start_offset_from_query(query: Option<&str>) -> Option<u64> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.strip_prefix("t="))
+        .find_map(|raw| parse_timecode(&urlencoding::decode(raw).ok()?).ok())
+}
+
 async fn handle_vod_master(
-    Path(vod_id): Path<String>,
+    VodId(vod_id): VodId,
+    uri: axum::http::Uri,
     State(state): State<ApiState>,
     headers: axum::http::HeaderMap,
 ) -> AppResult<Response> {
-    if !is_valid_id(&vod_id) {
-        return Err(AppError::BadRequest("Invalid VOD ID".to_string()));
-    }
+    let start_offset = start_offset_from_query(uri.query());
     let host = headers
         .get(header::HOST)
         .and_then(|h| h.to_str().ok())
         .unwrap_or("localhost")
         .to_string();
 
+    let settings = state.history.get_settings().await;
     let playlist = state
         .twitch
-        .generate_master_playlist(&vod_id, &host, &state.server_token)
+        .generate_master_playlist(
+            &vod_id,
+            &host,
+            &state.server_token,
+            settings.oauth_token.as_deref(),
+        )
         .await?;
 
     let body = if is_ios_family_request(&headers) {
@@ -289,7 +427,13 @@ async fn handle_vod_master(
     } else {
         playlist
     };
-    Ok(m3u8_response(body))
+    let mut response = m3u8_response(body);
+    if let Some(offset) = start_offset {
+        response
+            .headers_mut()
+            .insert(START_OFFSET_HEADER, HeaderValue::from(offset));
+    }
+    Ok(response)
 }
 
 async fn handle_live_master(
@@ -322,7 +466,7 @@ async fn handle_live_master(
 }
 
 async fn handle_proxy_variant(
-    Query(q): Query<VariantProxyQuery>,
+    ApiQuery(q): ApiQuery<VariantProxyQuery>,
     State(state): State<ApiState>,
 ) -> AppResult<Response> {
     let Some(id) = q.id else {
@@ -338,7 +482,7 @@ async fn handle_proxy_variant(
 }
 
 async fn handle_proxy_segment(
-    Query(q): Query<VariantProxyQuery>,
+    ApiQuery(q): ApiQuery<VariantProxyQuery>,
     State(state): State<ApiState>,
 ) -> AppResult<Response> {
     let settings = state.history.get_settings().await;
@@ -353,48 +497,115 @@ async fn handle_proxy_segment(
     };
 
     let mut builder = Response::builder();
-    if let Some(ct) = resp.headers().get(reqwest::header::CONTENT_TYPE) {
+    let upstream_ct = resp
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok());
+    if let Some(ct) = segment_content_type(resp.url().as_str(), upstream_ct) {
         builder = builder.header(reqwest::header::CONTENT_TYPE, ct);
     }
     if let Some(cc) = resp.headers().get(reqwest::header::CACHE_CONTROL) {
         builder = builder.header(reqwest::header::CACHE_CONTROL, cc);
     }
 
+    // Streamed rather than buffered: when the player cancels a segment the body
+    // is dropped, which drops the upstream response and closes its connection.
     let body = Body::from_stream(resp.bytes_stream());
     builder
         .body(body)
         .map_err(|e| AppError::Internal(e.to_string()))
 }
 
+/// Serves `body` tagged with `etag`, or an empty 304 when the client's
+/// `If-None-Match` already holds it. Callers take the tag from
+/// `HistoryStore::etag` before reading the data.
+fn etag_json<T: Serialize>(headers: &axum::http::HeaderMap, etag: String, body: T) -> Response {
+    let cached = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| {
+            v.split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == etag || tag == "*")
+        });
+    if cached {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+    ([(header::ETAG, etag)], Json(body)).into_response()
+}
+
 async fn handle_get_watchlist(
-    Query(q): Query<PagedQuery>,
+    ApiQuery(q): ApiQuery<PagedQuery>,
     State(state): State<ApiState>,
-) -> impl IntoResponse {
+    headers: axum::http::HeaderMap,
+) -> Response {
     let offset = q.offset.unwrap_or(0);
     let limit = q.limit.unwrap_or(100).clamp(1, 250);
 
+    let etag = state.history.etag();
     let (items, _total) = state.history.get_watchlist_paged(offset, limit).await;
-    Json(items)
+    etag_json(&headers, etag, items)
+}
+
+async fn handle_get_watchlist_enriched(State(state): State<ApiState>) -> AppResult<Response> {
+    let entries = state.history.get_watchlist().await;
+    let vod_ids = entries.iter().map(|e| e.vod_id.clone()).collect();
+    let fresh = state.twitch.fetch_vods_by_ids_strict(vod_ids).await?;
+    Ok(Json(enrich_watchlist(&entries, &fresh)).into_response())
+}
+
+/// Merges fresh metadata over the stored snapshots. Entries without fresh
+/// metadata keep their snapshot and are flagged `available: false`.
+fn enrich_watchlist(entries: &[WatchlistEntry], fresh: &[Vod]) -> Vec<Value> {
+    let by_id: std::collections::HashMap<&str, _> =
+        fresh.iter().map(|v| (v.id.as_str(), v)).collect();
+    entries
+        .iter()
+        .map(|entry| {
+            let vod = by_id.get(entry.vod_id.as_str());
+            serde_json::json!({
+                "vodId": entry.vod_id,
+                "title": vod.map_or(entry.title.as_str(), |v| v.title.as_str()),
+                "previewThumbnailURL": vod.map_or(entry.preview_thumbnail_url.as_str(), |v| v.preview_thumbnail_url.as_str()),
+                "lengthSeconds": vod.map_or(entry.length_seconds, |v| v.length_seconds),
+                "addedAt": entry.added_at,
+                "modifiedAt": entry.modified_at,
+                "available": vod.is_some(),
+                "vod": vod,
+            })
+        })
+        .collect()
 }
 
 async fn handle_add_watchlist(
     State(state): State<ApiState>,
-    Json(entry): Json<WatchlistEntry>,
+    ApiJson(mut entry): ApiJson<WatchlistEntry>,
 ) -> AppResult<Response> {
-    state.history.add_to_watchlist(entry).await?;
-    Ok(Json(serde_json::json!({ "ok": true })).into_response())
+    entry.vod_id = parse_vod_id(&entry.vod_id)?;
+    let (_, added) = state.history.add_to_watchlist(entry).await?;
+    Ok(Json(serde_json::json!({ "ok": true, "added": added })).into_response())
 }
 
 async fn handle_remove_watchlist(
-    Path(vod_id): Path<String>,
+    VodId(vod_id): VodId,
     State(state): State<ApiState>,
 ) -> AppResult<impl IntoResponse> {
     state.history.remove_from_watchlist(&vod_id).await?;
     Ok(Json(serde_json::json!({ "ok": true })))
 }
 
+async fn handle_mark_watchlist_watched(
+    VodId(vod_id): VodId,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    match state.history.mark_watchlist_watched(&vod_id).await? {
+        Some(watchlist) => Ok(Json(watchlist).into_response()),
+        None => Err(AppError::NotFound("VOD not in watchlist".to_string())),
+    }
+}
+
 async fn handle_get_settings(State(state): State<ApiState>) -> impl IntoResponse {
-    Json(state.history.get_settings().await)
+    Json(state.history.get_settings().await.redacted())
 }
 
 async fn handle_get_adblock_proxies(State(state): State<ApiState>) -> impl IntoResponse {
@@ -414,7 +625,7 @@ async fn handle_get_trusted_devices(State(state): State<ApiState>) -> impl IntoR
 async fn handle_set_trusted_device(
     Path(device_id): Path<String>,
     State(state): State<ApiState>,
-    Json(patch): Json<TrustedDevicePatch>,
+    ApiJson(patch): ApiJson<TrustedDevicePatch>,
 ) -> AppResult<Response> {
     match state
         .history
@@ -428,7 +639,7 @@ async fn handle_set_trusted_device(
 
 async fn handle_update_settings(
     State(state): State<ApiState>,
-    Json(patch): Json<SettingsPatch>,
+    ApiJson(patch): ApiJson<SettingsPatch>,
 ) -> AppResult<Response> {
     if let (Some(handle), Some(launch)) = (state.app_handle.as_ref(), patch.launch_at_login) {
         let manager = handle.autolaunch();
@@ -454,26 +665,35 @@ async fn handle_update_settings(
                 patch.launch_at_login,
                 patch.auto_update,
                 patch.enabled_extensions,
+                patch.trending,
+                patch.qr,
+                patch.oauth_token,
+                patch.preferred_language,
+                patch.bind_scope,
+                patch.exclude_reruns,
             )
-            .await?,
+            .await?
+            .redacted(),
     )
     .into_response())
 }
 
 async fn handle_get_subs(
-    Query(q): Query<PagedQuery>,
+    ApiQuery(q): ApiQuery<PagedQuery>,
     State(state): State<ApiState>,
-) -> impl IntoResponse {
+    headers: axum::http::HeaderMap,
+) -> Response {
     let offset = q.offset.unwrap_or(0);
     let limit = q.limit.unwrap_or(100).clamp(1, 250);
 
+    let etag = state.history.etag();
     let (items, _total) = state.history.get_subs_paged(offset, limit).await;
-    Json(items)
+    etag_json(&headers, etag, items)
 }
 
 async fn handle_add_sub(
     State(state): State<ApiState>,
-    Json(entry): Json<SubEntry>,
+    ApiJson(entry): ApiJson<SubEntry>,
 ) -> AppResult<Response> {
     if entry.login.is_empty() || entry.display_name.is_empty() || entry.profile_image_url.is_empty()
     {
@@ -483,6 +703,15 @@ async fn handle_add_sub(
     Ok(Json(serde_json::json!({ "ok": true })).into_response())
 }
 
+async fn handle_clear_subs(State(state): State<ApiState>) -> AppResult<Response> {
+    state.history.clear_subs().await?;
+    Ok(Json(Vec::<SubEntry>::new()).into_response())
+}
+
+async fn handle_subs_count(State(state): State<ApiState>) -> impl IntoResponse {
+    Json(serde_json::json!({ "count": state.history.subs_count().await }))
+}
+
 async fn handle_remove_sub(
     Path(login): Path<String>,
     State(state): State<ApiState>,
@@ -491,32 +720,208 @@ async fn handle_remove_sub(
     Ok(Json(serde_json::json!({ "ok": true })))
 }
 
+/// A stored sub's channel at a glance: its live stream (null when offline)
+/// alongside its VODs. Logins that aren't subs are a 404.
+async fn handle_get_sub_vods(
+    Path(login): Path<String>,
+    ApiQuery(thumb): ApiQuery<ThumbQuery>,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    let thumb = parse_thumb_size(thumb.thumb_width, thumb.thumb_height)?;
+    let login = login.trim().to_lowercase();
+    if !is_valid_login(&login) {
+        return Err(AppError::BadRequest("Invalid username".to_string()));
+    }
+    if state.history.get_sub(&login).await.is_none() {
+        return Err(AppError::NotFound("Not a sub".to_string()).with_code("NOT_A_SUB"));
+    }
+
+    let (live, vods) = tokio::join!(
+        state.twitch.fetch_user_live_stream(&login),
+        state.twitch.fetch_user_vods(&login, thumb)
+    );
+    // An unreachable live lookup shouldn't hide the VODs.
+    let live = live.ok().flatten();
+    Ok(Json(serde_json::json!({ "live": live, "vods": vods? })).into_response())
+}
+
+/// "New from your subs": every sub's recent VODs merged, newest first.
+async fn handle_subs_recent_vods(
+    ApiQuery(q): ApiQuery<PagedQuery>,
+    State(state): State<ApiState>,
+) -> impl IntoResponse {
+    let limit = q
+        .limit
+        .unwrap_or(SUBS_RECENT_VODS_DEFAULT_LIMIT)
+        .clamp(1, SUBS_RECENT_VODS_MAX_LIMIT);
+    let logins = state
+        .history
+        .get_subs()
+        .await
+        .into_iter()
+        .map(|s| s.login)
+        .collect();
+    Json(state.twitch.fetch_subs_recent_vods(logins, limit).await)
+}
+
+async fn handle_export_subs(
+    ApiQuery(q): ApiQuery<SubsExportQuery>,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    let subs = state.history.get_subs().await;
+    match q.format.as_deref().unwrap_or("json") {
+        "json" => Ok(Json(subs).into_response()),
+        "opml" => Ok(Response::builder()
+            .header(header::CONTENT_TYPE, "text/x-opml; charset=utf-8")
+            .header(
+                header::CONTENT_DISPOSITION,
+                "attachment; filename=\"nosubvod-subs.opml\"",
+            )
+            .body(Body::from(opml::subs_to_opml(&subs)))
+            .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())),
+        _ => Err(AppError::BadRequest(
+            "format must be json or opml".to_string(),
+        )),
+    }
+}
+
+/// Accepts either export format or a plain login list; bare logins are enriched
+/// through Twitch and unknown ones are reported back instead of stored.
+async fn handle_import_subs(State(state): State<ApiState>, body: String) -> AppResult<Response> {
+    let SubImportBody { entries, logins } = SubImportBody::parse(&body)?;
+    let mut entries: Vec<SubEntry> = entries
+        .into_iter()
+        .filter(|e| is_valid_login(&e.login.to_lowercase()))
+        .collect();
+
+    let resolved = state.twitch.resolve_sub_entries(logins.clone()).await;
+    let unresolved: Vec<String> = logins
+        .iter()
+        .map(|l| l.trim().trim_start_matches('@').to_lowercase())
+        .filter(|l| !resolved.iter().any(|e| &e.login == l))
+        .collect();
+    entries.extend(resolved);
+
+    let received = entries.len();
+    let added = state.history.add_subs_bulk(entries).await?;
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "added": added,
+        "skipped": received - added,
+        "unresolved": unresolved,
+    }))
+    .into_response())
+}
+
+/// Just the subs that are live right now, most viewers first.
+async fn handle_subs_live(State(state): State<ApiState>) -> impl IntoResponse {
+    let subs = state.history.get_subs().await;
+    let logins = subs.iter().map(|s| s.login.clone()).collect();
+    let live_status = state.twitch.fetch_live_status_by_logins(logins).await;
+    Json(live_subs(subs, &live_status.live))
+}
+
+/// Re-reads every sub's display name and avatar from Twitch. Subs whose
+/// account no longer exists are left in place and reported as `missing`.
+async fn handle_refresh_subs(State(state): State<ApiState>) -> AppResult<Response> {
+    let logins = state
+        .history
+        .get_subs()
+        .await
+        .into_iter()
+        .map(|s| s.login)
+        .collect();
+    let (fresh, missing) = state.twitch.refresh_sub_entries(logins).await;
+    let updated = state.history.update_sub_metadata(fresh).await?;
+    Ok(Json(serde_json::json!({
+        "ok": true,
+        "updated": updated,
+        "missing": missing,
+    }))
+    .into_response())
+}
+
+/// Following page in one call: subs + live status, reshaped into `{ live, offline }`.
+async fn handle_subs_sync_live(State(state): State<ApiState>) -> impl IntoResponse {
+    let subs = state.history.get_subs().await;
+    let logins = subs.iter().map(|s| s.login.clone()).collect();
+    let live_status = state.twitch.fetch_live_status_by_logins(logins).await;
+    Json(partition_subs_by_live(subs, &live_status.live))
+}
+
+/// Parses the shared `limit`/`cursor` search params.
+fn search_page_params(q: &SearchQuery) -> (usize, Option<String>) {
+    let limit = q
+        .limit
+        .as_deref()
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(20)
+        .clamp(1, 50);
+    let cursor = q
+        .cursor
+        .as_deref()
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty());
+    (limit, cursor)
+}
+
+/// Search results stay a plain JSON array; the next-page cursor travels in
+/// the `X-Next-Cursor` header.
+fn with_next_cursor(mut response: Response, next_cursor: Option<String>) -> Response {
+    if let Some(value) = next_cursor.and_then(|c| c.parse().ok()) {
+        response.headers_mut().insert("x-next-cursor", value);
+    }
+    response
+}
+
 async fn handle_search_channels(
-    Query(q): Query<SearchQuery>,
+    ApiQuery(q): ApiQuery<SearchQuery>,
     State(state): State<ApiState>,
 ) -> AppResult<Response> {
+    let (limit, cursor) = search_page_params(&q);
     let Some(query) = q.q.filter(|s| !s.is_empty()) else {
         return Ok(Json(Value::Array(vec![])).into_response());
     };
-    let results = state.twitch.search_channels(&query).await?;
-    Ok(Json(results).into_response())
+    let (results, next_cursor) = state
+        .twitch
+        .search_channels(&query, limit, cursor.as_deref())
+        .await?;
+    Ok(with_next_cursor(Json(results).into_response(), next_cursor))
 }
 
 async fn handle_search_global(
-    Query(q): Query<SearchQuery>,
+    ApiQuery(q): ApiQuery<SearchQuery>,
     State(state): State<ApiState>,
 ) -> AppResult<Response> {
+    let (limit, cursor) = search_page_params(&q);
     let Some(query) = q.q.filter(|s| !s.is_empty()) else {
         return Ok(Json(Value::Array(vec![])).into_response());
     };
-    let results = state.twitch.search_global_content(&query).await?;
-    Ok(Json(results).into_response())
+    let (results, next_cursor) = state
+        .twitch
+        .search_global_content(&query, limit, cursor.as_deref())
+        .await?;
+    Ok(with_next_cursor(Json(results).into_response(), next_cursor))
+}
+
+async fn handle_search_games(
+    ApiQuery(q): ApiQuery<SearchQuery>,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    let (limit, _) = search_page_params(&q);
+    let Some(query) = q.q.filter(|s| !s.trim().is_empty()) else {
+        return Ok(Json(Value::Array(vec![])).into_response());
+    };
+    let games = state.twitch.search_games(&query, limit).await?;
+    Ok(Json(games).into_response())
 }
 
 async fn handle_search_category_vods(
-    Query(q): Query<SearchCategoryQuery>,
+    ApiQuery(q): ApiQuery<SearchCategoryQuery>,
+    ApiQuery(thumb): ApiQuery<ThumbQuery>,
     State(state): State<ApiState>,
 ) -> AppResult<Response> {
+    let thumb = parse_thumb_size(thumb.thumb_width, thumb.thumb_height)?;
     let id = q.id.unwrap_or_default();
     let id = id.trim().to_string();
     let name = q.name.unwrap_or_default();
@@ -536,6 +941,16 @@ async fn handle_search_category_vods(
         .cursor
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty());
+    // Typed names rarely match a Twitch category exactly; an id already does.
+    let category = if id.is_empty() {
+        state.twitch.resolve_category(&name).await
+    } else {
+        None
+    };
+    let (name, id) = match &category {
+        Some(game) => (game.name.clone(), game.id.clone()),
+        None => (name, id),
+    };
     let (items, next_cursor, has_more) = state
         .twitch
         .fetch_category_vods_page(
@@ -547,24 +962,214 @@ async fn handle_search_category_vods(
             },
             limit,
             cursor.as_deref(),
+            thumb,
         )
         .await;
     Ok(Json(serde_json::json!({
         "items": items,
         "hasMore": has_more,
         "nextCursor": next_cursor,
+        "category": category,
     }))
     .into_response())
 }
 
-async fn handle_trends(State(state): State<ApiState>) -> AppResult<Response> {
+/// Resolves `duration == 0` entries through VOD metadata in the background
+/// and persists the lengths, so the request that noticed them isn't held up
+/// by the lookups; later requests read the filled-in durations.
+fn spawn_duration_backfill(state: &ApiState, entries: &[HistoryEntry]) {
+    let missing: Vec<String> = entries
+        .iter()
+        .filter(|e| e.duration <= 0.0)
+        .map(|e| e.vod_id.clone())
+        .collect();
+    if missing.is_empty() {
+        return;
+    }
+
+    let twitch = state.twitch.clone();
+    let history = state.history.clone();
+    tauri::async_runtime::spawn(async move {
+        let lengths = twitch.fetch_vod_lengths(missing).await;
+        if !lengths.is_empty() {
+            let _ = history.backfill_durations(&lengths).await;
+        }
+    });
+}
+
+/// Persists `lengths` for entries whose duration is unknown and patches
+/// `entries` to match.
+async fn apply_backfilled_durations(
+    state: &ApiState,
+    entries: &mut [HistoryEntry],
+    lengths: &std::collections::HashMap<String, u64>,
+) {
+    if lengths.is_empty() {
+        return;
+    }
+    let _ = state.history.backfill_durations(lengths).await;
+    for entry in entries.iter_mut().filter(|e| e.duration <= 0.0) {
+        if let Some(&length) = lengths.get(&entry.vod_id) {
+            entry.duration = length as f64;
+            entry.timecode = entry.timecode.min(entry.duration);
+        }
+    }
+}
+
+async fn handle_trends(
+    ApiQuery(q): ApiQuery<TrendsQuery>,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    let limit = q
+        .limit
+        .and_then(|s| s.parse::<usize>().ok())
+        .unwrap_or(TRENDING_DEFAULT_LIMIT)
+        .clamp(1, TRENDING_MAX_LIMIT);
+    let offset = q.offset.and_then(|s| s.parse::<usize>().ok()).unwrap_or(0);
     let (history, subs) = state.history.get_trending_input().await;
-    let results = state.twitch.fetch_trending_vods(history, subs).await?;
+    spawn_duration_backfill(&state, &history);
+    let settings = state.history.get_settings().await;
+    let exclusions = TrendsExclusions {
+        hidden: state.history.get_hidden_vods().await.into_iter().collect(),
+        blocklist: Blocklist::from_settings(&settings),
+    };
+    let mut prefs = settings.trending;
+    if let Some(min_length) = q.min_length.and_then(|s| s.parse::<u64>().ok()) {
+        prefs.min_vod_length_seconds = min_length;
+    }
+    let results = state
+        .twitch
+        .fetch_trending_vods(history, subs, &prefs, &exclusions, offset, limit)
+        .await?;
     Ok(Json(results).into_response())
 }
 
+/// Blocks (`POST`) or unblocks (`DELETE`) a channel and/or category in
+/// trends and live listings; answers with the updated lists.
+async fn handle_update_blocklist(
+    method: axum::http::Method,
+    State(state): State<ApiState>,
+    ApiJson(body): ApiJson<BlocklistBody>,
+) -> AppResult<Response> {
+    let channel = match body.channel.as_deref().map(str::trim) {
+        Some(raw) if !raw.is_empty() => {
+            let login = raw.trim_start_matches('@').to_lowercase();
+            if !is_valid_login(&login) {
+                return Err(AppError::BadRequest("Invalid channel login".to_string()));
+            }
+            Some(login)
+        }
+        _ => None,
+    };
+    let game = body
+        .game
+        .as_deref()
+        .map(str::trim)
+        .filter(|g| !g.is_empty());
+    if game.is_some_and(|g| g.chars().count() > 100) {
+        return Err(AppError::BadRequest("Invalid game name".to_string()));
+    }
+    if channel.is_none() && game.is_none() {
+        return Err(AppError::BadRequest(
+            "channel or game is required".to_string(),
+        ));
+    }
+
+    let blocked = method != axum::http::Method::DELETE;
+    let (channels, games) = state
+        .history
+        .update_blocklist(channel.as_deref(), game, blocked)
+        .await?;
+    Ok(Json(serde_json::json!({ "channels": channels, "games": games })).into_response())
+}
+
+async fn handle_get_hidden_vods(State(state): State<ApiState>) -> impl IntoResponse {
+    Json(state.history.get_hidden_vods().await)
+}
+
+async fn handle_hide_vod(
+    VodId(vod_id): VodId,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    let added = state.history.hide_vod(&vod_id).await?;
+    Ok(Json(serde_json::json!({ "ok": true, "added": added })).into_response())
+}
+
+async fn handle_unhide_vod(
+    VodId(vod_id): VodId,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    state.history.unhide_vod(&vod_id).await?;
+    Ok(Json(serde_json::json!({ "ok": true })).into_response())
+}
+
+async fn handle_trends_explain(
+    VodId(vod_id): VodId,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    let breakdown = state
+        .twitch
+        .explain_trending_vod(&vod_id)
+        .await
+        .ok_or_else(|| {
+            AppError::NotFound("VOD is not in the last trending candidate set".to_string())
+        })?;
+    Ok(Json(breakdown).into_response())
+}
+
+async fn handle_trending_categories(State(state): State<ApiState>) -> AppResult<Response> {
+    let (history, subs) = state.history.get_trending_input().await;
+    spawn_duration_backfill(&state, &history);
+    let categories = state
+        .twitch
+        .fetch_trending_categories(history, subs, 12)
+        .await?;
+    Ok(Json(categories).into_response())
+}
+
+/// "Your favorites" row: channels ranked by the same affinity trends uses.
+async fn handle_top_channels(
+    ApiQuery(q): ApiQuery<PagedQuery>,
+    State(state): State<ApiState>,
+) -> impl IntoResponse {
+    let limit = q.limit.unwrap_or(10).clamp(1, TOP_CHANNELS_MAX);
+    let (history, subs) = state.history.get_trending_input().await;
+    spawn_duration_backfill(&state, &history);
+    Json(state.twitch.fetch_top_channels(history, subs, limit).await)
+}
+
+async fn handle_live_viewers_trend(
+    Path(login): Path<String>,
+    ApiQuery(q): ApiQuery<PagedQuery>,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    let login = login.trim().to_lowercase();
+    if !is_valid_login(&login) {
+        return Err(AppError::BadRequest("Invalid channel login".to_string()));
+    }
+    let limit = q
+        .limit
+        .unwrap_or(VIEWER_TREND_MAX_SAMPLES)
+        .clamp(1, VIEWER_TREND_MAX_SAMPLES);
+    let samples = state.twitch.viewer_trend(&login, limit).await;
+    Ok(Json(serde_json::json!({ "login": login, "samples": samples })).into_response())
+}
+
+async fn handle_live_dvr_info(
+    Path(login): Path<String>,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    let login = login.trim().to_lowercase();
+    if !is_valid_login(&login) {
+        return Err(AppError::BadRequest("Invalid channel login".to_string()));
+    }
+    let settings = state.history.get_settings().await;
+    let info = state.twitch.fetch_live_dvr_info(&login, &settings).await?;
+    Ok(Json(info).into_response())
+}
+
 async fn handle_live(
-    Query(q): Query<LiveQuery>,
+    ApiQuery(q): ApiQuery<LiveQuery>,
     State(state): State<ApiState>,
 ) -> AppResult<Response> {
     let limit = q
@@ -579,10 +1184,35 @@ async fn handle_live(
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty());
 
-    let page = state
+    let settings = state.history.get_settings().await;
+    let exclude_reruns = match q.exclude_reruns.as_deref().map(str::trim) {
+        Some(v) if !v.is_empty() => matches!(v, "true" | "1"),
+        _ => settings.exclude_reruns,
+    };
+    let sort = match q.sort.as_deref().filter(|s| !s.trim().is_empty()) {
+        Some(raw) => Some(
+            LiveSort::parse(raw)
+                .ok_or_else(|| AppError::BadRequest(format!("Invalid sort: {raw}")))?,
+        ),
+        None => None,
+    };
+
+    let languages = match q.lang.as_deref() {
+        Some(raw) => parse_language_list(raw)?,
+        None => settings.preferred_language.clone().into_iter().collect(),
+    };
+
+    let mut page = state
         .twitch
-        .fetch_live_streams(limit, cursor.as_deref())
+        .fetch_live_streams(limit, cursor.as_deref(), &languages)
         .await?;
+    page.retain_unblocked(&Blocklist::from_settings(&settings));
+    if exclude_reruns {
+        page.retain_genuine_live();
+    }
+    if let Some(sort) = sort {
+        page.sort_items(sort);
+    }
     Ok(Json(page).into_response())
 }
 
@@ -592,13 +1222,20 @@ async fn handle_live_top_categories(State(state): State<ApiState>) -> AppResult<
 }
 
 async fn handle_live_category(
-    Query(q): Query<LiveCategoryQuery>,
+    ApiQuery(q): ApiQuery<LiveCategoryQuery>,
     State(state): State<ApiState>,
 ) -> AppResult<Response> {
+    let id = q.id.map(|s| s.trim().to_string()).filter(|s| !s.is_empty());
     let name = q.name.unwrap_or_default().trim().to_string();
-    if name.is_empty() {
-        return Err(AppError::BadRequest("Missing category name".to_string()));
-    }
+    // Ids are unambiguous, so they win when both are provided.
+    let category = match (id.as_deref(), name.as_str()) {
+        (Some(id), _) if !is_valid_id(id) => {
+            return Err(AppError::BadRequest("Invalid category id".to_string()))
+        }
+        (Some(id), _) => CategoryRef::Id(id),
+        (None, "") => return Err(AppError::BadRequest("Missing category name".to_string())),
+        (None, name) => CategoryRef::Name(name),
+    };
     let limit = q
         .limit
         .and_then(|s| s.parse::<usize>().ok())
@@ -609,15 +1246,18 @@ async fn handle_live_category(
         .cursor
         .map(|s| s.trim().to_string())
         .filter(|s| !s.is_empty());
-    let page = state
+    let mut page = state
         .twitch
-        .fetch_live_streams_by_category(&name, limit, cursor.as_deref())
+        .fetch_live_streams_by_category(category, limit, cursor.as_deref(), q.expand)
         .await?;
+    page.retain_unblocked(&Blocklist::from_settings(
+        &state.history.get_settings().await,
+    ));
     Ok(Json(page).into_response())
 }
 
 async fn handle_live_search(
-    Query(q): Query<LiveSearchQuery>,
+    ApiQuery(q): ApiQuery<LiveSearchQuery>,
     State(state): State<ApiState>,
 ) -> AppResult<Response> {
     let query = q.q.unwrap_or_default().trim().to_string();
@@ -637,13 +1277,13 @@ async fn handle_live_search(
 }
 
 async fn handle_live_status(
-    Query(q): Query<LiveStatusQuery>,
+    ApiQuery(q): ApiQuery<LiveStatusQuery>,
     State(state): State<ApiState>,
 ) -> impl IntoResponse {
     let raw = q.logins.unwrap_or_default();
     let raw = raw.trim().to_string();
     if raw.is_empty() {
-        return Json(serde_json::json!({})).into_response();
+        return Json(LiveStatusReport::default()).into_response();
     }
 
     let logins: Vec<String> = raw
@@ -651,19 +1291,82 @@ async fn handle_live_status(
         .map(|l| l.trim().to_lowercase())
         .filter(|l| !l.is_empty())
         .collect();
-
-    let result = state.twitch.fetch_live_status_by_logins(logins).await;
-    Json(result).into_response()
+
+    let result = state.twitch.fetch_live_status_by_logins(logins).await;
+    Json(result).into_response()
+}
+
+async fn handle_get_history(
+    State(state): State<ApiState>,
+    headers: axum::http::HeaderMap,
+) -> Response {
+    let etag = state.history.etag();
+    etag_json(&headers, etag, state.history.get_all_history().await)
+}
+
+/// Watch stats dashboard: totals plus watch time per game and per channel.
+async fn handle_get_history_stats(State(state): State<ApiState>) -> impl IntoResponse {
+    let entries: Vec<HistoryEntry> = state
+        .history
+        .get_all_history()
+        .await
+        .into_values()
+        .collect();
+    let vod_ids = entries.iter().map(|e| e.vod_id.clone()).collect();
+    let vods = state.twitch.fetch_history_vods(vod_ids).await;
+    Json(summarize_history(&entries, &vods))
+}
+
+/// VODs started but not finished, most recently watched first.
+async fn handle_continue_watching(
+    ApiQuery(q): ApiQuery<PagedQuery>,
+    State(state): State<ApiState>,
+) -> impl IntoResponse {
+    let limit = q.limit.unwrap_or(20).clamp(1, CONTINUE_WATCHING_MAX);
+    // Unknown durations stay in until the VOD length settles them.
+    let entries: Vec<HistoryEntry> = state
+        .history
+        .get_all_history()
+        .await
+        .into_values()
+        .filter(|e| e.duration <= 0.0 || is_partially_watched(e))
+        .collect();
+    let vod_ids = entries.iter().map(|e| e.vod_id.clone()).collect();
+    let vods = state.twitch.fetch_history_vods(vod_ids).await;
+    Json(continue_watching(entries, vods, limit))
 }
 
-async fn handle_get_history(State(state): State<ApiState>) -> impl IntoResponse {
-    Json::<std::collections::HashMap<String, crate::server::types::HistoryEntry>>(
-        state.history.get_all_history().await,
-    )
+/// Pushes `history-updated` / `watchlist-updated` so every open portal can
+/// refetch after a write made elsewhere; `data` is the store's new ETag. A
+/// client that falls too far behind gets both, i.e. "resync everything".
+/// Dropping the connection drops the receiver; writers never wait on it.
+/// The stream ends on quit, so open portals don't hold up the drain.
+async fn handle_events(
+    State(state): State<ApiState>,
+) -> Sse<impl futures::Stream<Item = Result<Event, std::convert::Infallible>>> {
+    let history = state.history.clone();
+    let stream = futures::stream::unfold(history.subscribe(), move |mut rx| {
+        let history = history.clone();
+        async move {
+            let events = match rx.recv().await {
+                Ok(event) => vec![event],
+                Err(RecvError::Lagged(_)) => StoreEvent::ALL.to_vec(),
+                Err(RecvError::Closed) => return None,
+            };
+            let etag = history.etag();
+            let batch = events
+                .into_iter()
+                .map(move |e| Ok(Event::default().event(e.name()).data(etag.clone())));
+            Some((futures::stream::iter(batch), rx))
+        }
+    })
+    .flatten()
+    .take_until(state.shutdown.clone().cancelled_owned());
+    Sse::new(stream).keep_alive(KeepAlive::default())
 }
 
 async fn handle_get_history_list(
-    Query(q): Query<HistoryListQuery>,
+    ApiQuery(q): ApiQuery<HistoryListQuery>,
     State(state): State<ApiState>,
 ) -> impl IntoResponse {
     let limit = q
@@ -674,13 +1377,23 @@ async fn handle_get_history_list(
 
     let offset = q.offset.and_then(|s| s.parse::<usize>().ok()).unwrap_or(0);
 
-    let (entries, _total) = state.history.get_history_paged(offset, limit).await;
+    let (mut entries, _total) = state.history.get_history_paged(offset, limit).await;
 
     let vod_ids: Vec<String> = entries.iter().map(|e| e.vod_id.clone()).collect();
     let metadata = state.twitch.fetch_vods_by_ids(vod_ids).await;
     let by_id: std::collections::HashMap<&str, _> =
         metadata.iter().map(|v| (v.id.as_str(), v)).collect();
 
+    // The metadata is already here, so backfill unknown durations for free.
+    let lengths: std::collections::HashMap<String, u64> = entries
+        .iter()
+        .filter(|e| e.duration <= 0.0)
+        .filter_map(|e| by_id.get(e.vod_id.as_str()))
+        .filter(|v| v.length_seconds > 0)
+        .map(|v| (v.id.clone(), v.length_seconds))
+        .collect();
+    apply_backfilled_durations(&state, &mut entries, &lengths).await;
+
     let enriched: Vec<_> = entries
         .iter()
         .map(|entry| {
@@ -698,7 +1411,7 @@ async fn handle_get_history_list(
 }
 
 async fn handle_get_history_vod(
-    Path(vod_id): Path<String>,
+    VodId(vod_id): VodId,
     State(state): State<ApiState>,
 ) -> impl IntoResponse {
     match state.history.get_history_by_vod_id(&vod_id).await {
@@ -707,13 +1420,22 @@ async fn handle_get_history_vod(
     }
 }
 
+async fn handle_sync_changes(
+    ApiQuery(q): ApiQuery<SyncChangesQuery>,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    let changes = state.history.get_sync_changes(q.since.unwrap_or(0)).await?;
+    Ok(Json(changes).into_response())
+}
+
 async fn handle_post_history(
     State(state): State<ApiState>,
-    Json(body): Json<HistoryBody>,
+    ApiJson(body): ApiJson<HistoryBody>,
 ) -> AppResult<Response> {
     let Some(vod_id) = body.vod_id else {
         return Err(AppError::BadRequest("Invalid parameters".to_string()));
     };
+    let vod_id = parse_vod_id(&vod_id)?;
     let Some(timecode) = body.timecode else {
         return Err(AppError::BadRequest("Invalid parameters".to_string()));
     };
@@ -723,7 +1445,11 @@ async fn handle_post_history(
         .history
         .update_history(&vod_id, timecode, duration)
         .await?;
-    Ok(Json(entry).into_response())
+    Ok(Json(HistoryWriteResponse {
+        entry,
+        server_time: now_ms()?,
+    })
+    .into_response())
 }
 
 async fn handle_get_user(
@@ -737,17 +1463,50 @@ async fn handle_get_user(
     Ok(Json(user).into_response())
 }
 
+async fn handle_resolve_user(
+    Path(username): Path<String>,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    // Display names can be localized, so only the length is checked here.
+    let username = username.trim();
+    if username.is_empty() || username.chars().count() > 64 {
+        return Err(AppError::BadRequest("Invalid username".to_string()));
+    }
+    let user = state.twitch.resolve_user(username).await?;
+    Ok(Json(user).into_response())
+}
+
+async fn handle_resolve_url(ApiJson(body): ApiJson<ResolveUrlBody>) -> AppResult<Response> {
+    let resolved = resolve_twitch_url(&body.url)
+        .ok_or_else(|| AppError::BadRequest("Unrecognized Twitch URL".to_string()))?;
+    Ok(Json(resolved).into_response())
+}
+
 async fn handle_get_user_vods(
     Path(username): Path<String>,
+    ApiQuery(thumb): ApiQuery<ThumbQuery>,
     State(state): State<ApiState>,
 ) -> AppResult<Response> {
     if !is_valid_login(&username) {
         return Err(AppError::BadRequest("Invalid username".to_string()));
     }
-    let vods = state.twitch.fetch_user_vods(&username).await?;
+    let thumb = parse_thumb_size(thumb.thumb_width, thumb.thumb_height)?;
+    let vods = state.twitch.fetch_user_vods(&username, thumb).await?;
     Ok(Json(vods).into_response())
 }
 
+/// Profile and live stream together, so the channel page needs one call.
+async fn handle_get_user_full(
+    Path(username): Path<String>,
+    State(state): State<ApiState>,
+) -> AppResult<Response> {
+    if !is_valid_login(&username) {
+        return Err(AppError::BadRequest("Invalid username".to_string()));
+    }
+    let full = state.twitch.fetch_user_full(&username).await?;
+    Ok(Json(full).into_response())
+}
+
 async fn handle_get_user_live(
     Path(username): Path<String>,
     State(state): State<ApiState>,
@@ -759,22 +1518,6 @@ async fn handle_get_user_live(
     Ok(Json(stream).into_response())
 }
 
-#[cfg(debug_assertions)]
-async fn handle_dev_portal_redirect(
-    headers: axum::http::HeaderMap,
-    uri: axum::http::Uri,
-) -> Redirect {
-    let host = headers
-        .get(header::HOST)
-        .and_then(|value| value.to_str().ok())
-        .unwrap_or("localhost");
-
-    let host_without_port = host.split(':').next().unwrap_or("localhost");
-    let path_and_query = uri.path_and_query().map(|pq| pq.as_str()).unwrap_or("/");
-
-    Redirect::temporary(&format!("https://{host_without_port}:5173{path_and_query}"))
-}
-
 async fn handle_shared_downloads(
     Path(file_path): Path<String>,
     State(state): State<ApiState>,
@@ -785,9 +1528,10 @@ async fn handle_shared_downloads(
         .download_local_path
         .or(settings.download_network_shared_path)
     else {
-        return Err(AppError::NotFound(
-            "Download path is not configured".to_string(),
-        ));
+        return Err(
+            AppError::NotFound("Download path is not configured".to_string())
+                .with_code("DOWNLOAD_PATH_UNSET"),
+        );
     };
 
     // Treat the configured download directory as the base path.
@@ -800,15 +1544,16 @@ async fn handle_shared_downloads(
     }
 
     // Resolve the base directory to an absolute, canonical path.
-    let base_dir_canon = tokio::fs::canonicalize(base_dir)
-        .await
-        .map_err(|_| AppError::NotFound("Download path is not configured".to_string()))?;
+    let base_dir_canon = tokio::fs::canonicalize(base_dir).await.map_err(|_| {
+        AppError::NotFound("Download path is not configured".to_string())
+            .with_code("DOWNLOAD_PATH_UNSET")
+    })?;
 
     // Join the base directory with the requested relative path, then canonicalize.
     let full_path = base_dir.join(requested_path);
-    let full_path_canon = tokio::fs::canonicalize(&full_path)
-        .await
-        .map_err(|_| AppError::NotFound("File not found".to_string()))?;
+    let full_path_canon = tokio::fs::canonicalize(&full_path).await.map_err(|_| {
+        AppError::NotFound("File not found".to_string()).with_code("FILE_NOT_FOUND")
+    })?;
 
     // Ensure the resolved path is still within the configured download directory.
     if !full_path_canon.starts_with(&base_dir_canon) {
@@ -825,7 +1570,7 @@ async fn handle_shared_downloads(
             }
             Ok(response)
         }
-        Err(_) => Err(AppError::NotFound("File not found".to_string())),
+        Err(_) => Err(AppError::NotFound("File not found".to_string()).with_code("FILE_NOT_FOUND")),
     }
 }
 
@@ -896,7 +1641,7 @@ async fn handle_get_active_downloads(State(state): State<ApiState>) -> impl Into
 async fn handle_live_chat_send(
     Path(login): Path<String>,
     State(state): State<ApiState>,
-    Json(body): Json<ChatSendBody>,
+    ApiJson(body): ApiJson<ChatSendBody>,
 ) -> AppResult<Response> {
     let message = body.message.trim().to_string();
     if message.is_empty() {
@@ -946,7 +1691,9 @@ async fn handle_live_chat_send(
     };
 
     if broadcaster_id.is_empty() {
-        return Err(AppError::NotFound("Channel not found".to_string()));
+        return Err(
+            AppError::NotFound("Channel not found".to_string()).with_code("CHANNEL_NOT_FOUND")
+        );
     }
 
     // Send via Helix chat messages API (requires user:write:chat scope)
@@ -997,7 +1744,11 @@ async fn handle_live_chat_send(
     } else {
         let status = resp.status();
         let body = resp.text().await.unwrap_or_default();
-        Ok((status, Json(serde_json::json!({ "error": body }))).into_response())
+        Ok((
+            status,
+            Json(serde_json::json!({ "error": body, "code": "TWITCH_UPSTREAM" })),
+        )
+            .into_response())
     }
 }
 
@@ -1014,15 +1765,18 @@ async fn handle_download_hls(
         .download_local_path
         .or(settings.download_network_shared_path)
     else {
-        return Err(AppError::NotFound(
-            "Download path is not configured".to_string(),
-        ));
+        return Err(
+            AppError::NotFound("Download path is not configured".to_string())
+                .with_code("DOWNLOAD_PATH_UNSET"),
+        );
     };
 
     let full_path = std::path::PathBuf::from(&base_path).join(&file_name);
     let file_size = match tokio::fs::metadata(&full_path).await {
         Ok(m) if m.is_file() => m.len(),
-        _ => return Err(AppError::NotFound("File not found".to_string())),
+        _ => {
+            return Err(AppError::NotFound("File not found".to_string()).with_code("FILE_NOT_FOUND"))
+        }
     };
 
     // Build a byte-range HLS playlist so hls.js can load the file progressively.
@@ -1055,7 +1809,7 @@ async fn handle_download_hls(
 
 async fn handle_start_download(
     State(state): State<ApiState>,
-    Json(req): Json<DownloadRequest>,
+    ApiJson(req): ApiJson<DownloadRequest>,
 ) -> AppResult<Response> {
     let settings = state.history.get_settings().await;
     let out_dir = resolve_download_output_dir(settings.download_local_path);
@@ -1127,7 +1881,7 @@ struct DevNotifyBody {
 
 async fn handle_dev_notify(
     State(state): State<ApiState>,
-    Json(body): Json<DevNotifyBody>,
+    ApiJson(body): ApiJson<DevNotifyBody>,
 ) -> AppResult<Response> {
     if let Some(app) = state.app_handle {
         app.emit("nsv-notification", &body)
@@ -1145,7 +1899,7 @@ struct DevLogBody {
     extension_id: String,
 }
 
-async fn handle_dev_log(Json(body): Json<DevLogBody>) -> impl IntoResponse {
+async fn handle_dev_log(ApiJson(body): ApiJson<DevLogBody>) -> impl IntoResponse {
     let level = body.level.to_lowercase();
     let msg = format!("[Extension:{}] {}", body.extension_id, body.message);
 
@@ -1160,8 +1914,50 @@ async fn handle_dev_log(Json(body): Json<DevLogBody>) -> impl IntoResponse {
     Json(serde_json::json!({ "success": true }))
 }
 
+async fn handle_get_config(State(state): State<ApiState>) -> impl IntoResponse {
+    let mut config = state.config.public_view();
+    config["port"] = super::SERVER_PORT.into();
+    config["trendsFeed"] = serde_json::json!({
+        "defaultLimit": TRENDING_DEFAULT_LIMIT,
+        "maxLimit": TRENDING_MAX_LIMIT,
+    });
+    Json(config)
+}
+
+async fn handle_health(State(state): State<ApiState>) -> impl IntoResponse {
+    Json(serde_json::json!({
+        "ok": true,
+        "version": env!("CARGO_PKG_VERSION"),
+        "portalAvailable": state.portal_available,
+        "ephemeral": state.history.is_ephemeral(),
+    }))
+}
+
+/// Lets the portal warn when the phone sits on another network (guest Wi-Fi,
+/// separate VLAN) than the desktop. `sameNetwork` is null when the client
+/// address is unknown.
+async fn handle_selftest(
+    State(state): State<ApiState>,
+    connect_info: Option<ConnectInfo<SocketAddr>>,
+    headers: axum::http::HeaderMap,
+) -> impl IntoResponse {
+    let server_ips = network::local_candidate_ips(state.config.ipv6);
+    let client = connect_info
+        .map(|ConnectInfo(peer)| client_ip(&headers, peer.ip(), &state.config.trusted_proxies));
+    Json(serde_json::json!({
+        "serverAddresses": server_ips,
+        "clientAddress": client,
+        "sameNetwork": client.map(|ip| network::shares_subnet(ip, &server_ips)),
+    }))
+}
+
 // ── Router factory ────────────────────────────────────────────────────────────
 
+/// Largest request body most routes accept; 413 beyond it.
+const API_BODY_LIMIT: usize = 2 * 1024 * 1024;
+/// Sub imports can carry a few thousand entries with avatars.
+const IMPORT_BODY_LIMIT: usize = 16 * 1024 * 1024;
+
 pub fn build_router(mut state: ApiState, portal_dist: Option<std::path::PathBuf>) -> Router {
     // Initialize download cache with 5s TTL
     state.download_cache = Cache::builder()
@@ -1188,20 +1984,28 @@ pub fn build_router(mut state: ApiState, portal_dist: Option<std::path::PathBuf>
             header::CONTENT_RANGE,
             header::CONTENT_LENGTH,
             header::ACCEPT_RANGES,
+            "x-next-cursor".parse().unwrap(),
+            START_OFFSET_HEADER.parse().unwrap(),
+            "x-response-time-ms".parse().unwrap(),
         ]);
 
-    // Auth callback must remain unauthenticated (Twitch redirects here)
-    let auth_callback = Router::new()
+    // Unauthenticated routes: Twitch redirects to the auth callback, and the
+    // health check must work without a token.
+    let public = Router::new()
         .route(
             "/auth/twitch/callback",
             get(crate::server::auth::handle_auth_callback),
         )
+        .route("/health", get(handle_health))
         .with_state(state.clone());
 
     let api = Router::new()
         // Video data
         .route("/vod/:vod_id/chat", get(handle_vod_chat))
+        .route("/vod/:vod_id/chat/range", get(handle_vod_chat_range))
         .route("/vod/:vod_id/markers", get(handle_vod_markers))
+        .route("/vod/:vod_id/chapters", get(handle_vod_chapters))
+        .route("/vods/batch", post(handle_vods_batch))
         .route("/vod/:vod_id/info", get(handle_vod_info))
         .route("/vod/:vod_id/master.m3u8", get(handle_vod_master))
         .route("/live/:login/master.m3u8", get(handle_live_master))
@@ -1226,12 +2030,20 @@ pub fn build_router(mut state: ApiState, portal_dist: Option<std::path::PathBuf>
             "/watchlist",
             get(handle_get_watchlist).post(handle_add_watchlist),
         )
+        .route("/watchlist/enriched", get(handle_get_watchlist_enriched))
         .route("/watchlist/:vod_id", delete(handle_remove_watchlist))
+        .route(
+            "/watchlist/:vod_id/watched",
+            post(handle_mark_watchlist_watched),
+        )
         // Settings
         .route(
             "/settings",
             get(handle_get_settings).post(handle_update_settings),
         )
+        .route("/config", get(handle_get_config))
+        .route("/selftest", get(handle_selftest))
+        .route("/qr", get(handle_get_qr))
         .route("/screenshare/state", get(handle_get_screenshare_state))
         .route("/screenshare/start", post(handle_start_screenshare))
         .route("/screenshare/stop", post(handle_stop_screenshare))
@@ -1248,19 +2060,50 @@ pub fn build_router(mut state: ApiState, portal_dist: Option<std::path::PathBuf>
         .route("/adblock/proxies", get(handle_get_adblock_proxies))
         .route("/adblock/status", get(handle_get_adblock_status))
         // Subs
-        .route("/subs", get(handle_get_subs).post(handle_add_sub))
+        .route(
+            "/subs",
+            get(handle_get_subs)
+                .post(handle_add_sub)
+                .delete(handle_clear_subs),
+        )
+        .route("/subs/count", get(handle_subs_count))
+        .route("/subs/live", get(handle_subs_live))
+        .route("/subs/sync-live", post(handle_subs_sync_live))
+        .route("/subs/recent-vods", get(handle_subs_recent_vods))
+        .route("/subs/refresh", post(handle_refresh_subs))
+        .route("/subs/export", get(handle_export_subs))
+        .route(
+            "/subs/import",
+            post(handle_import_subs).layer(DefaultBodyLimit::max(IMPORT_BODY_LIMIT)),
+        )
         .route("/subs/:login", delete(handle_remove_sub))
+        .route("/subs/:login/vods", get(handle_get_sub_vods))
         // Search
         .route("/search/channels", get(handle_search_channels))
         .route("/search/global", get(handle_search_global))
+        .route("/search/games", get(handle_search_games))
         .route("/search/category-vods", get(handle_search_category_vods))
         // Trends & Live
         .route("/trends", get(handle_trends))
+        .route("/insights/top-channels", get(handle_top_channels))
+        .route("/trends/explain/:vod_id", get(handle_trends_explain))
+        .route("/trends/hidden", get(handle_get_hidden_vods))
+        .route(
+            "/blocklist",
+            post(handle_update_blocklist).delete(handle_update_blocklist),
+        )
+        .route(
+            "/trends/hidden/:vod_id",
+            post(handle_hide_vod).delete(handle_unhide_vod),
+        )
+        .route("/categories/trending", get(handle_trending_categories))
         .route("/live", get(handle_live))
         .route("/live/top-categories", get(handle_live_top_categories))
         .route("/live/search", get(handle_live_search))
         .route("/live/category", get(handle_live_category))
         .route("/live/status", get(handle_live_status))
+        .route("/live/:login/viewers-trend", get(handle_live_viewers_trend))
+        .route("/live/:login/dvr-info", get(handle_live_dvr_info))
         .route("/live/:login/chat/send", post(handle_live_chat_send))
         // Twitch auth
         .route(
@@ -1289,14 +2132,21 @@ pub fn build_router(mut state: ApiState, portal_dist: Option<std::path::PathBuf>
             get(handle_get_history).post(handle_post_history),
         )
         .route("/history/list", get(handle_get_history_list))
+        .route("/history/stats", get(handle_get_history_stats))
+        .route("/history/continue", get(handle_continue_watching))
+        .route("/events", get(handle_events))
         .route("/history/:vod_id", get(handle_get_history_vod))
+        .route("/sync/changes", get(handle_sync_changes))
         // Extensions
         .route("/extensions", get(handle_get_extensions))
         .route("/extensions/:id/*file", get(handle_extension_files))
         // User
+        .route("/resolve", post(handle_resolve_url))
         .route("/user/:username", get(handle_get_user))
+        .route("/user/:username/resolve", get(handle_resolve_user))
         .route("/user/:username/vods", get(handle_get_user_vods))
         .route("/user/:username/live", get(handle_get_user_live))
+        .route("/user/:username/full", get(handle_get_user_full))
         // Auth middleware protects all these routes
         .layer(middleware::from_fn_with_state(
             state.clone(),
@@ -1314,31 +2164,43 @@ pub fn build_router(mut state: ApiState, portal_dist: Option<std::path::PathBuf>
         ))
         .with_state(state.clone());
 
-    let mut router = Router::new()
-        .nest("/api", auth_callback)
-        .nest("/api", api)
-        .nest("/api/dev", dev)
-        .layer(middleware::from_fn(security_headers_middleware))
+    // Unmatched `/api/*` paths get a JSON 404 instead of the portal fallback.
+    let api_root = Router::new()
+        .merge(public)
+        .merge(api)
+        .nest("/dev", dev)
+        .fallback(handle_api_not_found);
+    let router = Router::new().nest("/api", api_root);
+
+    // Layers go on after the portal mount so static assets get them too.
+    portal::mount_portal(router, portal_dist)
+        .layer(middleware::from_fn_with_state(
+            BasicAuthGate {
+                config: state.config.clone(),
+                server_token: state.server_token.clone(),
+            },
+            basic_auth_middleware,
+        ))
+        .layer(middleware::from_fn_with_state(
+            state.config.clone(),
+            security_headers_middleware,
+        ))
+        .layer(middleware::from_fn(request_timeout_middleware))
+        .layer(middleware::from_fn(response_time_middleware))
+        .layer(DefaultBodyLimit::max(API_BODY_LIMIT))
         .layer(TraceLayer::new_for_http())
-        .layer(CompressionLayer::new())
-        .layer(cors);
-
-    // Serve portal static files if available
-    if let Some(portal_path) = portal_dist {
-        if portal_path.exists() {
-            let serve_dir = ServeDir::new(&portal_path)
-                .append_index_html_on_directories(true)
-                .fallback(ServeFile::new(portal_path.join("index.html")));
-            router = router.nest_service("/", serve_dir);
-        }
-    } else {
-        #[cfg(debug_assertions)]
-        {
-            router = router.fallback(get(handle_dev_portal_redirect));
-        }
-    }
+        .layer(compression_layer())
+        .layer(cors)
+}
 
-    router
+/// gzip/brotli for JSON, playlists and portal assets. Proxied media segments
+/// are already compressed and streamed, so they pass through untouched.
+fn compression_layer() -> CompressionLayer<impl Predicate> {
+    CompressionLayer::new().compress_when(
+        DefaultPredicate::new()
+            .and(NotForContentType::const_new("video/"))
+            .and(NotForContentType::const_new("audio/")),
+    )
 }
 
 #[cfg(test)]
@@ -1348,8 +2210,15 @@ mod tests {
         body::Body,
         http::{Request, StatusCode},
     };
+    use std::sync::Arc;
     use tower::ServiceExt;
 
+    use crate::server::{
+        download::DownloadManager,
+        screenshare::ScreenShareService,
+        twitch::{mock_vod, spawn_gql_mock, TwitchService},
+    };
+
     // Helper to create a dummy state for testing
     async fn create_test_state() -> ApiState {
         let temp_dir = std::env::temp_dir().join(uuid::Uuid::new_v4().to_string());
@@ -1375,9 +2244,14 @@ mod tests {
             screenshare,
             extensions,
             oauth,
+            config: Arc::new(crate::server::config::ServerConfig::default()),
             server_token: "test_token".to_string(),
+            portal_url: "https://127.0.0.1:5173/?t=test_token".to_string(),
+            bind_scope: BindScope::Lan,
             app_handle: None,
             download_cache,
+            portal_available: true,
+            shutdown: tokio_util::sync::CancellationToken::new(),
         }
     }
 
@@ -1419,6 +2293,159 @@ mod tests {
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
 
+    #[tokio::test]
+    async fn history_and_watchlist_routes_reject_malformed_vod_ids() {
+        let app = build_router(create_test_state().await, None);
+
+        for (method, uri) in [
+            ("GET", "/api/history/abc"),
+            ("DELETE", "/api/watchlist/12%2034"),
+            ("POST", "/api/watchlist/1%22%7D/watched"),
+        ] {
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .method(method)
+                        .uri(uri)
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{method} {uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn history_and_watchlist_writes_validate_vod_ids() {
+        let state = create_test_state().await;
+        let app = build_router(state.clone(), None);
+        let post = |uri: &str, body: Value| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap()
+        };
+        let watchlist_entry = |vod_id: &str| {
+            serde_json::json!({
+                "vodId": vod_id,
+                "title": "VOD",
+                "previewThumbnailURL": "",
+                "lengthSeconds": 60,
+                "addedAt": 0
+            })
+        };
+
+        for request in [
+            post(
+                "/api/history",
+                serde_json::json!({ "vodId": "vod123", "timecode": 1.0 }),
+            ),
+            post("/api/watchlist", watchlist_entry("vod456")),
+        ] {
+            let uri = request.uri().clone();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+        }
+
+        // The `v` prefix from Twitch links is stored as the bare id, which
+        // the read and delete routes then find.
+        for request in [
+            post(
+                "/api/history",
+                serde_json::json!({ "vodId": "v123", "timecode": 1.0 }),
+            ),
+            post("/api/watchlist", watchlist_entry("v456")),
+        ] {
+            let uri = request.uri().clone();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{uri}");
+        }
+        let response = app
+            .clone()
+            .oneshot(
+                Request::builder()
+                    .uri("/api/history/123")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let entry: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(entry["vodId"], "123");
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("DELETE")
+                    .uri("/api/watchlist/456")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(state.history.get_watchlist().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn settings_patch_with_a_null_token_signs_out() {
+        let app = build_router(create_test_state().await, None);
+        let patch = |body: &str| {
+            let request = Request::builder()
+                .method("POST")
+                .uri("/api/settings")
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(body.to_string()))
+                .unwrap();
+            let response = app.clone().oneshot(request);
+            async move {
+                let response = response.await.unwrap();
+                assert_eq!(response.status(), StatusCode::OK);
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                    .await
+                    .unwrap();
+                serde_json::from_slice::<Value>(&bytes).unwrap()["hasOauthToken"] == true
+            }
+        };
+
+        assert!(patch(r#"{"oauthToken":"abc123xyz"}"#).await);
+        // Leaving the key out keeps the token.
+        assert!(patch(r#"{"oneSync":true}"#).await);
+        assert!(!patch(r#"{"oauthToken":null}"#).await);
+    }
+
+    #[tokio::test]
+    async fn event_stream_ends_on_shutdown() {
+        let state = create_test_state().await;
+        let shutdown = state.shutdown.clone();
+        let app = build_router(state, None);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/events")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        shutdown.cancel();
+        let body = tokio::time::timeout(
+            Duration::from_secs(2),
+            axum::body::to_bytes(response.into_body(), usize::MAX),
+        )
+        .await;
+        assert!(body.is_ok(), "event stream still open after shutdown");
+    }
+
     #[tokio::test]
     async fn handle_live_master_invalid_login() {
         let state = create_test_state().await;
@@ -1437,4 +2464,295 @@ mod tests {
 
         assert_eq!(response.status(), StatusCode::BAD_REQUEST);
     }
+
+    #[tokio::test]
+    async fn qr_route_renders_svg_and_png_and_rejects_unknown_formats() {
+        let app = build_router(create_test_state().await, None);
+        let get = |uri: &'static str| {
+            app.clone()
+                .oneshot(Request::builder().uri(uri).body(Body::empty()).unwrap())
+        };
+
+        let svg = get("/api/qr?format=svg&size=600").await.unwrap();
+        assert_eq!(svg.status(), StatusCode::OK);
+        assert_eq!(svg.headers()[header::CONTENT_TYPE], "image/svg+xml");
+        let body = axum::body::to_bytes(svg.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(std::str::from_utf8(&body).unwrap().contains("<svg"));
+
+        let png = get("/api/qr").await.unwrap();
+        assert_eq!(png.headers()[header::CONTENT_TYPE], "image/png");
+
+        let bad = get("/api/qr?format=gif").await.unwrap();
+        assert_eq!(bad.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn watchlist_get_answers_304_until_the_data_changes() {
+        let state = create_test_state().await;
+        let history = state.history.clone();
+        let app = build_router(state, None);
+        let get = |etag: Option<&str>| {
+            let mut req = Request::builder().uri("/api/watchlist");
+            if let Some(etag) = etag {
+                req = req.header(header::IF_NONE_MATCH, etag);
+            }
+            app.clone().oneshot(req.body(Body::empty()).unwrap())
+        };
+
+        let first = get(None).await.unwrap();
+        assert_eq!(first.status(), StatusCode::OK);
+        let etag = first.headers()[header::ETAG].to_str().unwrap().to_string();
+        assert_eq!(first.headers()[header::CACHE_CONTROL], "no-cache, private");
+
+        let unchanged = get(Some(&etag)).await.unwrap();
+        assert_eq!(unchanged.status(), StatusCode::NOT_MODIFIED);
+
+        history.update_history("123", 10.0, 60.0).await.unwrap();
+        let changed = get(Some(&etag)).await.unwrap();
+        assert_eq!(changed.status(), StatusCode::OK);
+        assert_ne!(changed.headers()[header::ETAG], etag.as_str());
+    }
+
+    #[test]
+    fn start_offset_skips_auth_tokens_sharing_the_t_param() {
+        let token = "0a1b2c3d4e5f60718293a4b5c6d7e8f9";
+        assert_eq!(
+            start_offset_from_query(Some(&format!("t={token}&t=1h2m3s&d=dev"))),
+            Some(3723)
+        );
+        assert_eq!(
+            start_offset_from_query(Some(&format!("t=600&t={token}"))),
+            Some(600)
+        );
+        assert_eq!(start_offset_from_query(Some(&format!("t={token}"))), None);
+        assert_eq!(start_offset_from_query(None), None);
+    }
+
+    #[tokio::test]
+    async fn post_history_reports_server_time() {
+        let state = create_test_state().await;
+        let app = build_router(state, None);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .method("POST")
+                    .uri("/api/history")
+                    .header(header::CONTENT_TYPE, "application/json")
+                    .body(Body::from(
+                        r#"{"vodId":"123","timecode":42.0,"duration":3600.0}"#,
+                    ))
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["vodId"], "123");
+        let updated_at = body["updatedAt"].as_u64().unwrap();
+        let server_time = body["serverTime"].as_u64().unwrap();
+        assert!(server_time >= updated_at && server_time - updated_at < 5_000);
+    }
+
+    #[test]
+    fn enriched_watchlist_refreshes_titles_and_flags_deleted_vods() {
+        let entry = |vod_id: &str, title: &str| WatchlistEntry {
+            vod_id: vod_id.to_string(),
+            title: title.to_string(),
+            preview_thumbnail_url: "https://static-cdn.jtvnw.net/old.jpg".to_string(),
+            length_seconds: 600,
+            added_at: 1,
+            modified_at: 1,
+        };
+        let entries = vec![entry("1", "Old title"), entry("2", "Deleted VOD")];
+        let fresh: Vod =
+            serde_json::from_value(mock_vod("1", serde_json::json!({ "title": "New title" })))
+                .unwrap();
+
+        let enriched = enrich_watchlist(&entries, &[fresh]);
+        assert_eq!(enriched[0]["title"], "New title");
+        assert_eq!(enriched[0]["lengthSeconds"], 7200);
+        assert_eq!(enriched[0]["available"], true);
+        assert_eq!(enriched[1]["title"], "Deleted VOD");
+        assert_eq!(enriched[1]["available"], false);
+        assert!(enriched[1]["vod"].is_null());
+    }
+
+    #[tokio::test]
+    async fn config_endpoint_reflects_env_overrides_without_secrets() {
+        // SHA-256 of "pw".
+        const DIGEST: &str = "30c952fab122c3f9759f02a6d95c3758b246b4fee239957b2d4fee46e26170c4";
+        let mut state = create_test_state().await;
+        state.config = Arc::new(crate::server::config::ServerConfig::from_lookup(
+            |key| match key {
+                "NOSUBVOD_IPV6" => Some("1".to_string()),
+                "NOSUBVOD_BASIC_AUTH_USER" => Some("family".to_string()),
+                "NOSUBVOD_BASIC_AUTH_SHA256" => Some(DIGEST.to_string()),
+                _ => None,
+            },
+        ));
+        let app = build_router(state, None);
+
+        let response = app
+            .oneshot(
+                Request::builder()
+                    .uri("/api/config")
+                    .header(header::AUTHORIZATION, "Basic ZmFtaWx5OnB3")
+                    .body(Body::empty())
+                    .unwrap(),
+            )
+            .await
+            .unwrap();
+        let status = response.status();
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let text = String::from_utf8(bytes.to_vec()).unwrap();
+        assert_eq!(status, StatusCode::OK, "{text}");
+
+        let body: Value = serde_json::from_str(&text).unwrap();
+        assert_eq!(body["ipv6"], true);
+        assert_eq!(body["port"], crate::server::SERVER_PORT);
+        assert_eq!(body["basicAuth"]["username"], "family");
+        assert!(!text.contains(DIGEST));
+    }
+
+    #[tokio::test]
+    async fn sub_vods_require_a_stored_sub_and_combine_live_and_vods() {
+        let mut state = create_test_state().await;
+        // Answers both the live and the VOD lookup of the channel.
+        let gql_url = spawn_gql_mock(|query| {
+            let user = if query.contains("videos(") {
+                let node = mock_vod("900", serde_json::json!({}));
+                serde_json::json!({ "videos": { "edges": [{ "node": node }] } })
+            } else {
+                serde_json::json!({
+                    "id": "1",
+                    "login": "streamer",
+                    "displayName": "Streamer",
+                    "stream": {
+                        "id": "live1",
+                        "title": "Live now",
+                        "viewersCount": 42,
+                        "createdAt": "2024-01-02T00:00:00Z",
+                    },
+                })
+            };
+            serde_json::json!({ "data": { "user": user } })
+        })
+        .await;
+        state.twitch = Arc::new(TwitchService::new().with_gql_url(gql_url));
+        state
+            .history
+            .add_sub(SubEntry {
+                login: "streamer".to_string(),
+                display_name: "Streamer".to_string(),
+                profile_image_url: String::new(),
+                modified_at: 0,
+            })
+            .await
+            .unwrap();
+        let app = build_router(state, None);
+
+        let get = |uri: &str| Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = app
+            .clone()
+            .oneshot(get("/api/subs/stranger/vods"))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let response = app.oneshot(get("/api/subs/Streamer/vods")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["live"]["id"], "live1");
+        assert_eq!(body["live"]["viewerCount"], 42);
+        assert_eq!(body["vods"][0]["id"], "900");
+    }
+
+    #[tokio::test]
+    async fn api_responses_are_compressed_when_the_client_accepts_it() {
+        let state = create_test_state().await;
+        let app = build_router(state, None);
+
+        let request = |encoding: Option<&str>| {
+            let mut req = Request::builder().uri("/api/health");
+            if let Some(encoding) = encoding {
+                req = req.header(header::ACCEPT_ENCODING, encoding);
+            }
+            req.body(Body::empty()).unwrap()
+        };
+
+        let response = app.clone().oneshot(request(Some("gzip"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_ENCODING], "gzip");
+
+        let response = app.oneshot(request(None)).await.unwrap();
+        assert!(response.headers().get(header::CONTENT_ENCODING).is_none());
+    }
+
+    #[tokio::test]
+    async fn oversized_bodies_are_rejected_except_on_sub_import() {
+        let state = create_test_state().await;
+        let app = build_router(state, None);
+        // Malformed JSON, so routes that do accept it fail fast at parsing.
+        let oversized = format!("[{}", "x".repeat(API_BODY_LIMIT));
+
+        let post = |uri: &str| {
+            Request::builder()
+                .method("POST")
+                .uri(uri)
+                .header(header::CONTENT_TYPE, "application/json")
+                .body(Body::from(oversized.clone()))
+                .unwrap()
+        };
+
+        let response = app.clone().oneshot(post("/api/history")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: Value = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!(body["code"], "PAYLOAD_TOO_LARGE");
+
+        // The import route lets the same body through to parsing.
+        let response = app.oneshot(post("/api/subs/import")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn malformed_bodies_and_queries_get_json_errors() {
+        let app = build_router(create_test_state().await, None);
+
+        let bad_body = Request::builder()
+            .method("POST")
+            .uri("/api/history")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from("{not json"))
+            .unwrap();
+        let bad_query = Request::builder()
+            .uri("/api/watchlist?limit=lots")
+            .body(Body::empty())
+            .unwrap();
+
+        for request in [bad_body, bad_query] {
+            let uri = request.uri().clone();
+            let response = app.clone().oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{uri}");
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            let body: Value = serde_json::from_slice(&bytes).unwrap();
+            assert_eq!(body["code"], "INVALID_INPUT", "{uri}");
+        }
+    }
 }
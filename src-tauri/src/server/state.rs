@@ -1,11 +1,12 @@
 use moka::future::Cache;
 use std::sync::Arc;
 use tauri::AppHandle;
+use tokio_util::sync::CancellationToken;
 
 use super::{
-    auth::OAuthStateStore, download::DownloadManager, dto::DownloadedFile,
+    auth::OAuthStateStore, config::ServerConfig, download::DownloadManager, dto::DownloadedFile,
     extensions::ExtensionManager, history::HistoryStore, screenshare::ScreenShareService,
-    twitch::TwitchService,
+    twitch::TwitchService, types::BindScope,
 };
 
 // ── Application state shared across all routes ─────────────────────────────────
@@ -18,9 +19,19 @@ pub struct ApiState {
     pub screenshare: Arc<ScreenShareService>,
     pub extensions: Arc<ExtensionManager>,
     pub oauth: Arc<OAuthStateStore>,
+    /// Startup configuration (environment overrides).
+    pub config: Arc<ServerConfig>,
     /// Per-session token required for API access (prevents unauthorized LAN access).
     pub server_token: String,
+    /// Portal URL (token included) encoded in the desktop QR code.
+    pub portal_url: String,
+    /// Interfaces the listeners were bound to for this session.
+    pub bind_scope: BindScope,
     pub app_handle: Option<AppHandle>,
     /// Cache for the downloads list (short TTL to avoid frequent disk scans)
     pub download_cache: Cache<String, Vec<DownloadedFile>>,
+    /// Result of the portal assets self-test run at startup.
+    pub portal_available: bool,
+    /// Cancelled on quit; open event streams end on it so the drain can finish.
+    pub shutdown: CancellationToken,
 }
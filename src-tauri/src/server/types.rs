@@ -1,5 +1,5 @@
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 // ── Server info ────────────────────────────────────────────────────────────────
 
@@ -8,7 +8,29 @@ pub struct ServerInfo {
     pub ip: String,
     pub port: u16,
     pub url: String,
+    /// Every advertised portal URL, `url` first (IPv6 ones when enabled).
+    #[serde(rename = "candidateUrls", default)]
+    pub candidate_urls: Vec<String>,
     pub qrcode: String,
+    /// False when the release build could not find the portal assets.
+    #[serde(rename = "portalAvailable")]
+    pub portal_available: bool,
+    /// Interfaces the listeners were bound to for this session.
+    #[serde(rename = "bindScope", default)]
+    pub bind_scope: BindScope,
+}
+
+/// Which interfaces the API and portal listen on. Read at startup, so a
+/// change takes effect on the next launch.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BindScope {
+    /// Every interface, so phones on the network can open the portal.
+    #[default]
+    Lan,
+    /// Loopback only: nothing is reachable from another machine, and there
+    /// is no portal QR code.
+    Localhost,
 }
 
 // ── Twitch types ───────────────────────────────────────────────────────────────
@@ -23,6 +45,27 @@ pub struct UserInfo {
     pub profile_image_url: String,
 }
 
+/// `GET /api/user/:username/full`: a profile and its live stream in one call.
+#[derive(Debug, Clone, Serialize)]
+pub struct UserFull {
+    pub user: UserInfo,
+    #[serde(rename = "isLive")]
+    pub is_live: bool,
+    pub live: Option<LiveStream>,
+}
+
+/// A channel's weight in the trends preference profile
+/// (`/api/insights/top-channels`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChannelAffinity {
+    pub login: String,
+    #[serde(rename = "displayName")]
+    pub display_name: String,
+    #[serde(rename = "profileImageURL")]
+    pub profile_image_url: String,
+    pub score: f64,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VodGame {
     pub name: String,
@@ -98,6 +141,123 @@ pub struct LiveGame {
     pub box_art_url: Option<String>,
 }
 
+/// Game returned by `/api/search/games`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GameInfo {
+    pub id: String,
+    pub name: String,
+    #[serde(rename = "boxArtURL", default)]
+    pub box_art_url: String,
+    #[serde(rename = "viewersCount", default)]
+    pub viewers_count: u64,
+}
+
+/// Size of the VOD thumbnails Twitch returns (`previewThumbnailURL`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ThumbSize {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl ThumbSize {
+    pub const DEFAULT: ThumbSize = ThumbSize {
+        width: 320,
+        height: 180,
+    };
+    /// Sizes clients may ask for; a short list keeps the caches bounded.
+    pub const ALLOWED: [ThumbSize; 5] = [
+        ThumbSize {
+            width: 160,
+            height: 90,
+        },
+        ThumbSize::DEFAULT,
+        ThumbSize {
+            width: 480,
+            height: 270,
+        },
+        ThumbSize {
+            width: 640,
+            height: 360,
+        },
+        ThumbSize {
+            width: 1280,
+            height: 720,
+        },
+    ];
+}
+
+impl Default for ThumbSize {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl std::fmt::Display for ThumbSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+/// Automatic game-change segment of a VOD (`/api/vod/:vod_id/chapters`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Chapter {
+    #[serde(rename = "positionMilliseconds")]
+    pub position_milliseconds: u64,
+    #[serde(rename = "durationMilliseconds")]
+    pub duration_milliseconds: u64,
+    pub description: String,
+    pub game: Option<ChapterGame>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ChapterGame {
+    pub name: String,
+    #[serde(rename = "boxArtURL", default)]
+    pub box_art_url: String,
+}
+
+/// Category entry for the personalized categories feed; `personalized` marks
+/// games that rank because of the user's watch history.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrendingCategory {
+    #[serde(flatten)]
+    pub game: LiveGame,
+    pub personalized: bool,
+}
+
+/// Per-signal contributions behind a trending VOD's score. `score` is the
+/// component sum scaled by `quality` and `language_multiplier`, except when
+/// the quality gate trips (`quality < 0.05`), where the score is `quality`
+/// scaled by `language_multiplier` alone.
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScoreBreakdown {
+    pub popularity: f64,
+    pub game_affinity: f64,
+    pub channel_affinity: f64,
+    pub lang_affinity: f64,
+    pub fr_boost: f64,
+    pub sub_boost: f64,
+    pub recency: f64,
+    pub quality: f64,
+    /// User override for the VOD's language (`settings.trending`), 1.0 by default.
+    pub language_multiplier: f64,
+    pub score: f64,
+}
+
+impl ScoreBreakdown {
+    /// Sum of the additive signals, before the quality multiplier.
+    pub fn base(&self) -> f64 {
+        self.popularity
+            + self.game_affinity
+            + self.channel_affinity
+            + self.lang_affinity
+            + self.fr_boost
+            + self.sub_boost
+            + self.recency
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LiveBroadcaster {
     pub id: String,
@@ -108,6 +268,29 @@ pub struct LiveBroadcaster {
     pub profile_image_url: String,
 }
 
+/// Twitch stream `type`. Anything unrecognised (including the empty string
+/// Twitch sends for regular streams on some paths) maps to `None`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LiveStreamType {
+    Live,
+    Rerun,
+    WatchParty,
+    Premiere,
+}
+
+impl LiveStreamType {
+    pub fn from_gql(raw: Option<&str>) -> Option<Self> {
+        match raw?.trim().to_ascii_lowercase().as_str() {
+            "live" => Some(Self::Live),
+            "rerun" => Some(Self::Rerun),
+            "watch_party" | "watchparty" => Some(Self::WatchParty),
+            "premiere" => Some(Self::Premiere),
+            _ => None,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LiveStream {
     pub id: String,
@@ -120,6 +303,15 @@ pub struct LiveStream {
     pub language: Option<String>,
     #[serde(rename = "startedAt")]
     pub started_at: String,
+    #[serde(
+        rename = "streamType",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub stream_type: Option<LiveStreamType>,
+    /// Streamer-set `freeformTags` names, in Twitch's order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
     pub broadcaster: LiveBroadcaster,
     pub game: Option<LiveGame>,
 }
@@ -133,8 +325,103 @@ pub struct LiveStreamsPage {
     pub has_more: bool,
 }
 
+/// Viewer count of a live stream as seen by one `/api/live` fetch.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ViewerSample {
+    /// Unix time in milliseconds.
+    pub at: u64,
+    pub viewers: u64,
+}
+
+/// Slice of the cached trends feed; `nextOffset` is set while more remain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrendingPage {
+    pub items: Vec<Vod>,
+    #[serde(rename = "hasMore")]
+    pub has_more: bool,
+    #[serde(rename = "nextOffset")]
+    pub next_offset: Option<usize>,
+}
+
+impl LiveStream {
+    /// True unless Twitch flagged the stream as a rerun, watch party or premiere.
+    pub fn is_genuine_live(&self) -> bool {
+        matches!(self.stream_type, None | Some(LiveStreamType::Live))
+    }
+}
+
+impl LiveStreamsPage {
+    pub fn retain_genuine_live(&mut self) {
+        self.items.retain(LiveStream::is_genuine_live);
+    }
+
+    pub fn retain_unblocked(&mut self, blocklist: &Blocklist) {
+        self.items.retain(|s| !blocklist.blocks_stream(s));
+    }
+
+    /// Twitch can't order `streams` this way, so only this page is re-sorted:
+    /// pages are not ordered relative to each other.
+    pub fn sort_items(&mut self, sort: LiveSort) {
+        match sort {
+            LiveSort::ViewersDesc => self
+                .items
+                .sort_by(|a, b| b.viewer_count.cmp(&a.viewer_count)),
+            LiveSort::ViewersAsc => self
+                .items
+                .sort_by(|a, b| a.viewer_count.cmp(&b.viewer_count)),
+            // RFC 3339 timestamps in UTC sort lexicographically.
+            LiveSort::Recent => self.items.sort_by(|a, b| b.started_at.cmp(&a.started_at)),
+        }
+    }
+}
+
+/// `sort` query parameter of `/api/live`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LiveSort {
+    ViewersDesc,
+    ViewersAsc,
+    Recent,
+}
+
+impl LiveSort {
+    pub fn parse(raw: &str) -> Option<Self> {
+        match raw.trim().to_ascii_lowercase().as_str() {
+            "viewers_desc" => Some(Self::ViewersDesc),
+            "viewers_asc" => Some(Self::ViewersAsc),
+            "recent" => Some(Self::Recent),
+            _ => None,
+        }
+    }
+}
+
 pub type LiveStatusMap = HashMap<String, LiveStream>;
 
+/// Batch live-status check (`/api/live/status`). A login missing from `live`
+/// is offline only if it is in `checked`; `failed` lookups say nothing.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LiveStatusReport {
+    pub live: LiveStatusMap,
+    pub checked: Vec<String>,
+    pub failed: Vec<String>,
+}
+
+/// How far back a live stream can be rewound (`/api/live/:login/dvr-info`).
+/// `available` is false when Twitch only serves the usual few-second edge.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct DvrInfo {
+    pub available: bool,
+    pub window_seconds: f64,
+}
+
+/// Following page payload: live subs as full streams (most viewers first),
+/// offline subs with their stored display info.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubsLiveSync {
+    pub live: Vec<LiveStream>,
+    pub offline: Vec<SubEntry>,
+}
+
 // ── Persistence ────────────────────────────────────────────────────────────────
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -147,6 +434,43 @@ pub struct HistoryEntry {
     pub updated_at: u64,
 }
 
+impl HistoryEntry {
+    /// Share of the VOD watched, in `0.0..=1.0`; `None` while the duration
+    /// is unknown.
+    pub fn progress(&self) -> Option<f64> {
+        (self.duration > 0.0).then(|| (self.timecode / self.duration).clamp(0.0, 1.0))
+    }
+}
+
+/// Stored entry plus the server clock, so clients can measure their skew
+/// before reconciling last-write-wins timestamps.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryWriteResponse {
+    #[serde(flatten)]
+    pub entry: HistoryEntry,
+    #[serde(rename = "serverTime")]
+    pub server_time: u64,
+}
+
+/// Watch time attributed to one game or channel in [`HistoryStats`].
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct WatchTimeShare {
+    pub name: String,
+    pub seconds: f64,
+}
+
+/// `/api/history/stats`. Entries whose VOD is gone count in the totals but
+/// not in the breakdowns, which are sorted by watch time.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryStats {
+    pub total_entries: usize,
+    pub total_watched_seconds: f64,
+    pub completed: usize,
+    pub by_game: Vec<WatchTimeShare>,
+    pub by_channel: Vec<WatchTimeShare>,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HistoryVodEntry {
     #[serde(flatten)]
@@ -165,6 +489,8 @@ pub struct WatchlistEntry {
     pub length_seconds: u64,
     #[serde(rename = "addedAt", default)]
     pub added_at: u64,
+    #[serde(rename = "modifiedAt", default)]
+    pub modified_at: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -174,6 +500,53 @@ pub struct SubEntry {
     pub display_name: String,
     #[serde(rename = "profileImageURL")]
     pub profile_image_url: String,
+    #[serde(rename = "modifiedAt", default)]
+    pub modified_at: u64,
+}
+
+/// Collections whose removals are recorded for `/api/sync/changes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SyncCollection {
+    Watchlist,
+    Subs,
+}
+
+/// Marks a removed watchlist VOD or sub so other devices can drop it too.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SyncTombstone {
+    pub collection: SyncCollection,
+    /// `vodId` for the watchlist, `login` for subs.
+    pub key: String,
+    #[serde(rename = "removedAt")]
+    pub removed_at: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncRemovals {
+    pub watchlist: Vec<String>,
+    pub subs: Vec<String>,
+}
+
+/// Latest change timestamp per collection, removals included.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SyncMarkers {
+    pub history: u64,
+    pub watchlist: u64,
+    pub subs: u64,
+}
+
+/// Unified delta of everything changed after `since`.
+#[derive(Debug, Clone, Serialize)]
+pub struct SyncChanges {
+    #[serde(rename = "serverTime")]
+    pub server_time: u64,
+    pub since: u64,
+    pub history: Vec<HistoryEntry>,
+    pub watchlist: Vec<WatchlistEntry>,
+    pub subs: Vec<SubEntry>,
+    pub removed: SyncRemovals,
+    pub markers: SyncMarkers,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -192,6 +565,172 @@ pub struct TrustedDevice {
     pub trusted: bool,
 }
 
+/// User tuning for the trends feed, stored under `settings.trending`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct TrendingPreferences {
+    /// Score multiplier per language code (lowercase). Unlisted languages
+    /// keep 1.0; 0.0 drops that language from the feed entirely.
+    #[serde(rename = "languageMultipliers", default)]
+    pub language_multipliers: HashMap<String, f64>,
+    /// VODs shorter than this are heavily penalized; a tenth of it is the
+    /// point below which they are near-invisible. 0 disables the length gate.
+    #[serde(rename = "minLengthSeconds", default = "default_min_length_seconds")]
+    pub min_length_seconds: u64,
+    /// VODs shorter than this never reach scoring. 0 keeps every length; the
+    /// `minLengthSeconds` penalty still applies to whatever passes.
+    #[serde(rename = "minVodLengthSeconds", default)]
+    pub min_vod_length_seconds: u64,
+    /// VODs with fewer views are penalized. 0 disables the view gate.
+    #[serde(rename = "minViewCount", default = "default_min_view_count")]
+    pub min_view_count: u64,
+    /// Use linear instead of quadratic ramps below the gates.
+    #[serde(rename = "softenRamps", default)]
+    pub soften_ramps: bool,
+    /// Shuffle VODs of similar score so the feed varies between sessions.
+    #[serde(rename = "shuffleWithinBands", default)]
+    pub shuffle_within_bands: bool,
+    /// Bounds on the share of non-French VODs, which otherwise follows the
+    /// language mix of the watch history.
+    #[serde(rename = "foreignRatioMin", default = "default_foreign_ratio_min")]
+    pub foreign_ratio_min: f64,
+    #[serde(rename = "foreignRatioMax", default = "default_foreign_ratio_max")]
+    pub foreign_ratio_max: f64,
+    /// Fixed non-French share, ignoring history and the bounds above.
+    #[serde(
+        rename = "foreignRatio",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub foreign_ratio: Option<f64>,
+    /// Feed slots per channel: subs and watched channels get
+    /// `maxSlotsFavored`, every other channel `maxSlotsOther`. Channels
+    /// related to favorites get one more than `maxSlotsOther`, at most
+    /// `maxSlotsFavored`.
+    #[serde(rename = "maxSlotsFavored", default = "default_max_slots_favored")]
+    pub max_slots_favored: usize,
+    #[serde(rename = "maxSlotsOther", default = "default_max_slots_other")]
+    pub max_slots_other: usize,
+    /// Category names always fetched for trends, ahead of the ones taken
+    /// from watch history.
+    #[serde(rename = "pinnedGames", default)]
+    pub pinned_games: Vec<String>,
+}
+
+/// Upper bound for the per-channel slot caps in [`TrendingPreferences`].
+pub const MAX_CHANNEL_SLOTS: usize = 10;
+/// Most categories that can be pinned in [`TrendingPreferences`].
+pub const MAX_PINNED_GAMES: usize = 5;
+
+impl TrendingPreferences {
+    /// Diversity-pass slots for a channel, from the most to the least
+    /// favored tier.
+    pub fn channel_slots(&self, is_favorite: bool, is_related: bool) -> usize {
+        if is_favorite {
+            self.max_slots_favored
+        } else if is_related {
+            (self.max_slots_other + 1).min(self.max_slots_favored.max(self.max_slots_other))
+        } else {
+            self.max_slots_other
+        }
+    }
+
+    /// Whether a VOD is long enough to be a trends candidate at all.
+    pub fn admits_length(&self, length_seconds: u64) -> bool {
+        length_seconds >= self.min_vod_length_seconds
+    }
+}
+
+fn default_min_length_seconds() -> u64 {
+    600
+}
+
+fn default_min_view_count() -> u64 {
+    50
+}
+
+fn default_foreign_ratio_min() -> f64 {
+    0.16
+}
+
+fn default_foreign_ratio_max() -> f64 {
+    0.4
+}
+
+fn default_max_slots_favored() -> usize {
+    4
+}
+
+fn default_max_slots_other() -> usize {
+    2
+}
+
+impl Default for TrendingPreferences {
+    fn default() -> Self {
+        Self {
+            language_multipliers: HashMap::new(),
+            min_length_seconds: default_min_length_seconds(),
+            min_vod_length_seconds: 0,
+            min_view_count: default_min_view_count(),
+            soften_ramps: false,
+            shuffle_within_bands: false,
+            foreign_ratio_min: default_foreign_ratio_min(),
+            foreign_ratio_max: default_foreign_ratio_max(),
+            foreign_ratio: None,
+            max_slots_favored: default_max_slots_favored(),
+            max_slots_other: default_max_slots_other(),
+            pinned_games: Vec::new(),
+        }
+    }
+}
+
+/// How the portal QR code on the desktop is rendered, stored under
+/// `settings.qr`.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QrPreferences {
+    /// Minimum width/height in pixels. Modules are scaled by whole pixels,
+    /// so the image can come out slightly larger.
+    #[serde(default = "default_qr_size")]
+    pub size: u32,
+    #[serde(rename = "errorCorrection", default)]
+    pub error_correction: QrErrorCorrection,
+}
+
+pub const QR_MIN_SIZE: u32 = 200;
+pub const QR_MAX_SIZE: u32 = 1200;
+
+fn default_qr_size() -> u32 {
+    400
+}
+
+impl Default for QrPreferences {
+    fn default() -> Self {
+        Self {
+            size: default_qr_size(),
+            error_correction: QrErrorCorrection::default(),
+        }
+    }
+}
+
+/// QR error-correction level; higher levels survive glare and blur better
+/// at the cost of a denser code.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+pub enum QrErrorCorrection {
+    L,
+    #[default]
+    M,
+    Q,
+    H,
+}
+
+impl TrendingPreferences {
+    pub fn language_multiplier(&self, language: &str) -> f64 {
+        self.language_multipliers
+            .get(language)
+            .copied()
+            .unwrap_or(1.0)
+    }
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct ExperienceSettings {
     #[serde(rename = "oneSync")]
@@ -243,6 +782,112 @@ pub struct ExperienceSettings {
     pub auto_update: bool,
     #[serde(rename = "enabledExtensions", default)]
     pub enabled_extensions: Vec<String>,
+    #[serde(default)]
+    pub trending: TrendingPreferences,
+    #[serde(default)]
+    pub qr: QrPreferences,
+    /// User-supplied Twitch auth token sent as `Authorization: OAuth <token>`
+    /// on playback requests, for sub-only content. Encrypted on disk and
+    /// never returned by the API (see [`ExperienceSettings::redacted`]).
+    #[serde(
+        rename = "oauthToken",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub oauth_token: Option<String>,
+    /// Broadcaster language `/api/live` filters on when the request has no
+    /// `lang` param. `None` shows every language.
+    #[serde(
+        rename = "preferredLanguage",
+        default,
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub preferred_language: Option<String>,
+    #[serde(rename = "bindScope", default)]
+    pub bind_scope: BindScope,
+    /// Channel logins and category names kept out of trends and live
+    /// listings, matched case-insensitively. Edited through `/api/blocklist`.
+    #[serde(rename = "blockedChannels", default)]
+    pub blocked_channels: Vec<String>,
+    #[serde(rename = "blockedGames", default)]
+    pub blocked_games: Vec<String>,
+    /// Default for `/api/live`'s `excludeReruns` param.
+    #[serde(rename = "excludeReruns", default)]
+    pub exclude_reruns: bool,
+    /// Set only on redacted copies, in place of `oauthToken`.
+    #[serde(
+        rename = "hasOauthToken",
+        default,
+        skip_deserializing,
+        skip_serializing_if = "std::ops::Not::not"
+    )]
+    pub has_oauth_token: bool,
+}
+
+impl ExperienceSettings {
+    /// Copy safe to send to clients: the auth token is replaced by
+    /// `hasOauthToken`.
+    pub fn redacted(mut self) -> Self {
+        self.has_oauth_token = self.oauth_token.take().is_some();
+        self
+    }
+}
+
+/// Most entries in each of `blockedChannels` and `blockedGames`.
+pub const MAX_BLOCKLIST_ENTRIES: usize = 500;
+
+/// Lowercased lookup form of the blocked channels and games in settings.
+#[derive(Debug, Clone, Default)]
+pub struct Blocklist {
+    channels: HashSet<String>,
+    games: HashSet<String>,
+}
+
+impl Blocklist {
+    pub fn from_settings(settings: &ExperienceSettings) -> Self {
+        let lower = |names: &[String]| names.iter().map(|n| n.to_lowercase()).collect();
+        Self {
+            channels: lower(&settings.blocked_channels),
+            games: lower(&settings.blocked_games),
+        }
+    }
+
+    pub fn blocks(&self, login: Option<&str>, game: Option<&str>) -> bool {
+        login.is_some_and(|l| self.channels.contains(&l.to_lowercase()))
+            || game.is_some_and(|g| self.games.contains(&g.to_lowercase()))
+    }
+
+    pub fn blocks_vod(&self, vod: &Vod) -> bool {
+        self.blocks(
+            vod.owner.as_ref().map(|o| o.login.as_str()),
+            vod.game.as_ref().map(|g| g.name.as_str()),
+        )
+    }
+
+    pub fn blocks_stream(&self, stream: &LiveStream) -> bool {
+        self.blocks(
+            Some(&stream.broadcaster.login),
+            stream.game.as_ref().map(|g| g.name.as_str()),
+        )
+    }
+
+    /// Stable text form, for cache keys.
+    pub fn fingerprint(&self) -> String {
+        let sorted = |set: &HashSet<String>| {
+            let mut names: Vec<&str> = set.iter().map(String::as_str).collect();
+            names.sort_unstable();
+            names.join(",")
+        };
+        format!("{}|{}", sorted(&self.channels), sorted(&self.games))
+    }
+}
+
+/// What a trends feed leaves out: VODs the user hid and anything from a
+/// blocked channel or category.
+#[derive(Debug, Clone, Default)]
+pub struct TrendsExclusions {
+    pub hidden: HashSet<String>,
+    pub blocklist: Blocklist,
 }
 
 /// Root of the persisted JSON file.
@@ -265,4 +910,9 @@ pub struct PersistedData {
         skip_serializing_if = "Option::is_none"
     )]
     pub twitch_token: Option<String>,
+    #[serde(rename = "syncTombstones", default)]
+    pub sync_tombstones: Vec<SyncTombstone>,
+    /// VOD ids the user never wants to see in trends, oldest first.
+    #[serde(rename = "hiddenVods", default)]
+    pub hidden_vods: Vec<String>,
 }
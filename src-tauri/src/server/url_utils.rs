@@ -1,5 +1,9 @@
 use std::borrow::Cow;
 
+use serde::Serialize;
+
+use super::validation::{is_valid_id, is_valid_login};
+
 pub fn extract_origin(url: &str) -> Cow<'_, str> {
     if let Some(sep) = url.find("://") {
         let after = &url[sep + 3..];
@@ -29,10 +33,192 @@ pub fn resolve_url<'a>(raw: &'a str, origin: &str, base_url: &str) -> Cow<'a, st
     Cow::Owned(format!("{base_dir}{raw}"))
 }
 
+/// Content-Type for a proxied media segment, derived from the target URL
+/// extension. The upstream value is kept when it agrees with the extension
+/// (it may carry parameters) or when the extension is unknown and the upstream
+/// type is an actual media type rather than `application/octet-stream`.
+pub fn segment_content_type(target_url: &str, upstream: Option<&str>) -> Option<String> {
+    let path = target_url
+        .split(['?', '#'])
+        .next()
+        .unwrap_or("")
+        .to_ascii_lowercase();
+
+    let from_extension = [
+        (".ts", "video/mp2t"),
+        (".mp4", "video/mp4"),
+        (".m4s", "video/mp4"),
+        (".aac", "audio/aac"),
+        (".mp3", "audio/mpeg"),
+        (".vtt", "text/vtt"),
+        (".m3u8", "application/vnd.apple.mpegurl"),
+    ]
+    .iter()
+    .find(|(ext, _)| path.ends_with(ext))
+    .map(|(_, content_type)| *content_type);
+
+    let upstream = upstream.map(str::trim).filter(|ct| !ct.is_empty());
+    let essence = |ct: &str| {
+        ct.split(';')
+            .next()
+            .unwrap_or("")
+            .trim()
+            .to_ascii_lowercase()
+    };
+
+    match (from_extension, upstream) {
+        (Some(expected), Some(up)) if essence(up) == expected => Some(up.to_string()),
+        (Some(expected), _) => Some(expected.to_string()),
+        (None, Some(up)) => {
+            let up_essence = essence(up);
+            let sensible = up_essence.starts_with("video/")
+                || up_essence.starts_with("audio/")
+                || up_essence == "application/vnd.apple.mpegurl"
+                || up_essence == "text/vtt";
+            sensible.then(|| up.to_string())
+        }
+        (None, None) => None,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TwitchUrlKind {
+    Vod,
+    Channel,
+    Clip,
+}
+
+/// What a pasted Twitch link points at: a VOD id, a channel login or a clip
+/// slug, depending on `kind`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ResolvedTwitchUrl {
+    pub kind: TwitchUrlKind,
+    #[serde(rename = "idOrLogin")]
+    pub id_or_login: String,
+}
+
+/// First path segments on twitch.tv that are site pages, not channels.
+const RESERVED_TWITCH_PATHS: [&str; 10] = [
+    "directory",
+    "downloads",
+    "drops",
+    "inventory",
+    "jobs",
+    "p",
+    "search",
+    "settings",
+    "subscriptions",
+    "wallet",
+];
+
+/// Recognizes `twitch.tv/videos/<id>`, `twitch.tv/<login>/video/<id>`,
+/// `twitch.tv/<login>/clip/<slug>`, `clips.twitch.tv/<slug>` and
+/// `twitch.tv/<login>`, with or without scheme or `www.`/`m.` prefix.
+pub fn resolve_twitch_url(raw: &str) -> Option<ResolvedTwitchUrl> {
+    let raw = raw.trim();
+    let with_scheme = if raw.contains("://") {
+        raw.to_string()
+    } else {
+        format!("https://{raw}")
+    };
+    let url = reqwest::Url::parse(&with_scheme).ok()?;
+    if !matches!(url.scheme(), "http" | "https") {
+        return None;
+    }
+    let host = url.host_str()?.to_ascii_lowercase();
+    let segments: Vec<&str> = url
+        .path_segments()
+        .map(|s| s.filter(|seg| !seg.is_empty()).collect())
+        .unwrap_or_default();
+
+    let resolved = |kind, value: &str| {
+        Some(ResolvedTwitchUrl {
+            kind,
+            id_or_login: value.to_string(),
+        })
+    };
+    let is_clip_slug = |slug: &str| {
+        !slug.is_empty()
+            && slug.len() <= 100
+            && slug
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+    };
+
+    if host == "clips.twitch.tv" {
+        return match segments.as_slice() {
+            [slug] if is_clip_slug(slug) => resolved(TwitchUrlKind::Clip, slug),
+            _ => None,
+        };
+    }
+    if !matches!(host.as_str(), "twitch.tv" | "www.twitch.tv" | "m.twitch.tv") {
+        return None;
+    }
+
+    match segments.as_slice() {
+        ["videos", id] | [_, "video", id] if is_valid_id(id) => resolved(TwitchUrlKind::Vod, id),
+        [_, "clip", slug] if is_clip_slug(slug) => resolved(TwitchUrlKind::Clip, slug),
+        [login, ..] => {
+            let login = login.to_ascii_lowercase();
+            if RESERVED_TWITCH_PATHS.contains(&login.as_str())
+                || login == "videos"
+                || !is_valid_login(&login)
+            {
+                return None;
+            }
+            resolved(TwitchUrlKind::Channel, &login)
+        }
+        [] => None,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn segment_content_type_follows_target_extension() {
+        let ts = "https://video-edge.abc.ttvnw.net/v1/segment/CmX.ts?token=1";
+        assert_eq!(
+            segment_content_type(ts, None).as_deref(),
+            Some("video/mp2t")
+        );
+        assert_eq!(
+            segment_content_type(ts, Some("application/octet-stream")).as_deref(),
+            Some("video/mp2t")
+        );
+
+        let mp4 = "https://d2nvs31859zcd8.cloudfront.net/abc/chunked/init-0.mp4";
+        assert_eq!(
+            segment_content_type(mp4, None).as_deref(),
+            Some("video/mp4")
+        );
+        assert_eq!(
+            segment_content_type(mp4, Some("binary/octet-stream")).as_deref(),
+            Some("video/mp4")
+        );
+    }
+
+    #[test]
+    fn segment_content_type_keeps_sensible_upstream_values() {
+        let ts = "https://cdn.example.com/a/1.ts";
+        assert_eq!(
+            segment_content_type(ts, Some("video/MP2T; charset=binary")).as_deref(),
+            Some("video/MP2T; charset=binary")
+        );
+
+        let unknown = "https://cdn.example.com/v1/segment/abc";
+        assert_eq!(
+            segment_content_type(unknown, Some("video/mp2t")).as_deref(),
+            Some("video/mp2t")
+        );
+        assert_eq!(
+            segment_content_type(unknown, Some("application/octet-stream")),
+            None
+        );
+    }
+
     #[test]
     fn extracts_origin_with_port() {
         let origin = extract_origin("https://example.com:8443/path/file.m3u8");
@@ -96,4 +282,60 @@ mod tests {
         let url = resolve_url("index.m3u8", "https://host.local", "master.m3u8");
         assert_eq!(url, "master.m3u8index.m3u8");
     }
+
+    #[test]
+    fn resolves_vod_channel_and_clip_links() {
+        let cases = [
+            (
+                "https://www.twitch.tv/videos/123456789",
+                TwitchUrlKind::Vod,
+                "123456789",
+            ),
+            ("twitch.tv/videos/42?t=1h2m3s", TwitchUrlKind::Vod, "42"),
+            (
+                "https://m.twitch.tv/somechannel/video/77",
+                TwitchUrlKind::Vod,
+                "77",
+            ),
+            (
+                "https://www.twitch.tv/SomeChannel",
+                TwitchUrlKind::Channel,
+                "somechannel",
+            ),
+            (
+                "https://www.twitch.tv/somechannel/videos",
+                TwitchUrlKind::Channel,
+                "somechannel",
+            ),
+            (
+                "https://clips.twitch.tv/FunnyClipSlug-abc_123",
+                TwitchUrlKind::Clip,
+                "FunnyClipSlug-abc_123",
+            ),
+            (
+                "https://www.twitch.tv/somechannel/clip/OtherSlug",
+                TwitchUrlKind::Clip,
+                "OtherSlug",
+            ),
+        ];
+        for (url, kind, value) in cases {
+            let resolved = resolve_twitch_url(url).unwrap_or_else(|| panic!("{url}"));
+            assert_eq!(resolved.kind, kind, "{url}");
+            assert_eq!(resolved.id_or_login, value, "{url}");
+        }
+    }
+
+    #[test]
+    fn rejects_foreign_hosts_site_pages_and_malformed_ids() {
+        for url in [
+            "https://youtube.com/videos/123",
+            "https://www.twitch.tv/",
+            "https://www.twitch.tv/directory",
+            "https://www.twitch.tv/videos/notanumber",
+            "ftp://twitch.tv/videos/1",
+            "https://eviltwitch.tv/videos/1",
+        ] {
+            assert_eq!(resolve_twitch_url(url), None, "{url}");
+        }
+    }
 }
@@ -1,15 +1,173 @@
-use axum::http::header;
+use axum::{
+    async_trait,
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        FromRequest, FromRequestParts, Path, Query, Request,
+    },
+    http::{header, request::Parts},
+    Json,
+};
+
+use super::error::AppError;
+use super::types::ThumbSize;
 
 /// Returns true if the string looks like a valid VOD / numeric ID.
 pub fn is_valid_id(s: &str) -> bool {
     !s.is_empty() && s.len() <= 20 && s.chars().all(|c| c.is_ascii_digit())
 }
 
+/// Trims and validates a VOD id before it is interpolated into GQL queries.
+/// The `v2012345678` form from Twitch links is accepted and stored as digits.
+pub fn parse_vod_id(raw: &str) -> Result<String, AppError> {
+    let trimmed = raw.trim();
+    let id = trimmed.strip_prefix(['v', 'V']).unwrap_or(trimmed);
+    if !is_valid_id(id) {
+        return Err(AppError::BadRequest("Invalid VOD ID".to_string()));
+    }
+    Ok(id.to_string())
+}
+
+/// `:vod_id` path parameter, rejected with 400 unless it passes [`parse_vod_id`].
+#[derive(Debug, Clone)]
+pub struct VodId(pub String);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for VodId {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Path(raw) = Path::<String>::from_request_parts(parts, state)
+            .await
+            .map_err(|_| AppError::BadRequest("Invalid VOD ID".to_string()))?;
+        parse_vod_id(&raw).map(VodId)
+    }
+}
+
+/// [`Json`] body extractor whose rejections are [`AppError`]s, so a malformed
+/// body gets the same `{ error, code }` JSON as any other API error.
+#[derive(Debug, Clone)]
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        Ok(ApiJson(value))
+    }
+}
+
+/// [`Query`] extractor whose rejections are [`AppError`]s, like [`ApiJson`].
+#[derive(Debug, Clone)]
+pub struct ApiQuery<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    Query<T>: FromRequestParts<S, Rejection = QueryRejection>,
+    S: Send + Sync,
+{
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let Query(value) = Query::<T>::from_request_parts(parts, state).await?;
+        Ok(ApiQuery(value))
+    }
+}
+
 /// Returns true if the string looks like a valid Twitch login/username.
 pub fn is_valid_login(s: &str) -> bool {
     !s.is_empty() && s.len() <= 25 && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
+/// Parses a comma-separated list of broadcaster language codes (`fr,en`,
+/// `zh-hk`), lowercased and deduplicated. Blank entries are skipped.
+pub fn parse_language_list(raw: &str) -> Result<Vec<String>, AppError> {
+    let mut languages: Vec<String> = Vec::new();
+    for code in raw.split(',').map(|c| c.trim().to_ascii_lowercase()) {
+        if code.is_empty() {
+            continue;
+        }
+        let valid = (2..=8).contains(&code.len())
+            && code.chars().all(|c| c.is_ascii_lowercase() || c == '-');
+        if !valid {
+            return Err(AppError::BadRequest(format!("Invalid language: {code}")));
+        }
+        if !languages.contains(&code) {
+            languages.push(code);
+        }
+    }
+    Ok(languages)
+}
+
+/// `thumbWidth` / `thumbHeight` query params. Either one picks the allowed
+/// size it belongs to; both absent means [`ThumbSize::DEFAULT`].
+pub fn parse_thumb_size(width: Option<u32>, height: Option<u32>) -> Result<ThumbSize, AppError> {
+    if width.is_none() && height.is_none() {
+        return Ok(ThumbSize::DEFAULT);
+    }
+    ThumbSize::ALLOWED
+        .into_iter()
+        .find(|size| {
+            width.is_none_or(|w| w == size.width) && height.is_none_or(|h| h == size.height)
+        })
+        .ok_or_else(|| {
+            let allowed: Vec<String> = ThumbSize::ALLOWED.iter().map(ToString::to_string).collect();
+            AppError::BadRequest(format!(
+                "Unsupported thumbnail size; use one of {}",
+                allowed.join(", ")
+            ))
+        })
+}
+
+/// Parses a start offset given as plain seconds (`600`) or Twitch-style
+/// `1h2m3s` (any subset, in that order) into seconds.
+pub fn parse_timecode(raw: &str) -> Result<u64, AppError> {
+    let invalid = || AppError::BadRequest(format!("Invalid timecode: {raw}"));
+    let value = raw.trim().to_ascii_lowercase();
+    if value.is_empty() {
+        return Err(invalid());
+    }
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Ok(seconds);
+    }
+
+    let mut total: u64 = 0;
+    let mut digits = String::new();
+    let mut last_unit = 0u8;
+    for c in value.chars() {
+        if c.is_ascii_digit() {
+            digits.push(c);
+            continue;
+        }
+        let (rank, multiplier) = match c {
+            'h' => (1, 3600),
+            'm' => (2, 60),
+            's' => (3, 1),
+            _ => return Err(invalid()),
+        };
+        if digits.is_empty() || rank <= last_unit {
+            return Err(invalid());
+        }
+        let amount: u64 = digits.parse().map_err(|_| invalid())?;
+        total = amount
+            .checked_mul(multiplier)
+            .and_then(|v| total.checked_add(v))
+            .ok_or_else(invalid)?;
+        digits.clear();
+        last_unit = rank;
+    }
+    if !digits.is_empty() {
+        return Err(invalid());
+    }
+    Ok(total)
+}
+
 pub fn is_ios_family_request(headers: &axum::http::HeaderMap) -> bool {
     let ua = headers
         .get(header::USER_AGENT)
@@ -83,6 +241,16 @@ mod tests {
         assert!(!is_valid_login("a".repeat(26).as_str()));
     }
 
+    #[test]
+    fn thumb_sizes_come_from_the_allowlist() {
+        assert_eq!(parse_thumb_size(None, None).unwrap(), ThumbSize::DEFAULT);
+        let retina = parse_thumb_size(Some(640), None).unwrap();
+        assert_eq!((retina.width, retina.height), (640, 360));
+        assert_eq!(parse_thumb_size(None, Some(90)).unwrap().width, 160);
+        assert!(parse_thumb_size(Some(640), Some(180)).is_err());
+        assert!(parse_thumb_size(Some(4096), None).is_err());
+    }
+
     #[test]
     fn test_is_ios_family_request() {
         let mut headers = HeaderMap::new();
@@ -118,4 +286,90 @@ chunklist_w111.m3u8
         assert!(!filtered.contains("chunklist_w111.m3u8"));
         assert!(filtered.contains("avc1.42e01e"));
     }
+
+    #[test]
+    fn parse_language_list_normalizes_and_rejects_garbage() {
+        assert_eq!(
+            parse_language_list(" FR, en,,fr ,zh-hk").unwrap(),
+            vec!["fr", "en", "zh-hk"]
+        );
+        assert!(parse_language_list("").unwrap().is_empty());
+        assert!(parse_language_list("fr,e\"n").is_err());
+        assert!(parse_language_list("x").is_err());
+    }
+
+    #[test]
+    fn parse_timecode_accepts_seconds_and_twitch_style() {
+        assert_eq!(parse_timecode("600").unwrap(), 600);
+        assert_eq!(parse_timecode("1h2m3s").unwrap(), 3723);
+        assert_eq!(parse_timecode(" 2M ").unwrap(), 120);
+        assert_eq!(parse_timecode("1h30s").unwrap(), 3630);
+        for raw in ["", "abc", "1m2h", "1h1h", "h", "10m5", "-5", "1.5s"] {
+            assert!(parse_timecode(raw).is_err(), "{raw:?} should be rejected");
+        }
+    }
+
+    #[test]
+    fn parse_vod_id_trims_and_rejects_malformed_ids() {
+        assert_eq!(parse_vod_id(" 2012345678 ").unwrap(), "2012345678");
+        assert_eq!(parse_vod_id("v2012345678").unwrap(), "2012345678");
+        for raw in [
+            "", "   ", "12 34", "abc", "v", "vv123", "vod123", "123;drop", "\"123\"", "123\n",
+        ] {
+            assert!(parse_vod_id(raw).is_err(), "{raw:?} should be rejected");
+        }
+    }
+
+    #[tokio::test]
+    async fn malformed_vod_ids_are_rejected_with_400() {
+        use axum::{body::Body, http::Request, http::StatusCode, routing::get, Router};
+        use tower::ServiceExt;
+
+        async fn handler(VodId(id): VodId) -> String {
+            id
+        }
+
+        let app = Router::new()
+            .route("/api/vod/:vod_id/chat", get(handler))
+            .route("/api/vod/:vod_id/markers", get(handler))
+            .route("/api/vod/:vod_id/master.m3u8", get(handler));
+
+        for route in ["chat", "markers", "master.m3u8"] {
+            for id in [
+                "%20%20",
+                "abc",
+                "12%2034",
+                "1%22%7D",
+                "123456789012345678901",
+            ] {
+                let response = app
+                    .clone()
+                    .oneshot(
+                        Request::builder()
+                            .uri(format!("/api/vod/{id}/{route}"))
+                            .body(Body::empty())
+                            .unwrap(),
+                    )
+                    .await
+                    .unwrap();
+                assert_eq!(
+                    response.status(),
+                    StatusCode::BAD_REQUEST,
+                    "{id} on {route}"
+                );
+            }
+
+            let response = app
+                .clone()
+                .oneshot(
+                    Request::builder()
+                        .uri(format!("/api/vod/2012345678/{route}"))
+                        .body(Body::empty())
+                        .unwrap(),
+                )
+                .await
+                .unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{route}");
+        }
+    }
 }
@@ -1,10 +1,11 @@
-import React, { useEffect, useState } from 'react';
+import React, { useCallback, useEffect, useState } from 'react';
 import { useNavigate, type NavigateFunction } from 'react-router-dom';
 import { HistoryVodEntry } from '../../shared/types';
 import { Download as DownloadIcon } from 'lucide-react';
 import DownloadMenu from './components/DownloadMenu';
 import { formatRelative } from '../../shared/utils/formatters';
 import { TopBar } from './components/TopBar';
+import { useStoreEvents } from './hooks/useStoreEvents';
 
 type HistoryItemProps = Readonly<{ entry: HistoryVodEntry; navigate: NavigateFunction }>;
 
@@ -83,7 +84,7 @@ export default function History() {
   const [loading, setLoading] = useState(true);
   const [error, setError] = useState('');
 
-  useEffect(() => {
+  const loadHistory = useCallback(() => {
     fetch('/api/history/list')
       .then((res) => {
         if (!res.ok) throw new Error('Failed to load history');
@@ -91,6 +92,7 @@ export default function History() {
       })
       .then((data: HistoryVodEntry[]) => {
         setItems(data);
+        setError('');
         setLoading(false);
       })
       .catch((err: Error) => {
@@ -99,6 +101,13 @@ export default function History() {
       });
   }, []);
 
+  useEffect(() => {
+    loadHistory();
+  }, [loadHistory]);
+
+  // Progress saved from another device shows up without a reload.
+  useStoreEvents(['history-updated'], () => loadHistory());
+
   return (
     <>
       <TopBar mode="back" title="Watch History" />
@@ -1,9 +1,10 @@
-import React, { useEffect, useState } from 'react';
+import React, { useCallback, useEffect, useState } from 'react';
 import { useNavigate } from 'react-router-dom';
 import {
   ExperienceSettings,
   HistoryVodEntry,
   LiveStatusMap,
+  LiveStatusReport,
   SubEntry,
   UserInfo,
   WatchlistEntry,
@@ -13,6 +14,7 @@ import MySubsList from './components/home/MySubsList';
 import HistoryPreview from './components/home/HistoryPreview';
 import WatchlistPreview from './components/home/WatchlistPreview';
 import { TopBar } from './components/TopBar';
+import { useStoreEvents } from './hooks/useStoreEvents';
 
 const defaultSettings: ExperienceSettings = {
   oneSync: false,
@@ -76,6 +78,20 @@ export default function Home() {
     void loadData();
   }, []);
 
+  // Another device added, removed or watched something: refresh the previews.
+  const refreshWatchlist = useCallback(async () => {
+    const res = await fetch('/api/watchlist');
+    if (res.ok) setWatchlist((await res.json()) as WatchlistEntry[]);
+  }, []);
+  const refreshHistoryPreview = useCallback(async () => {
+    const res = await fetch('/api/history/list?limit=3');
+    if (res.ok) setHistoryPreview((await res.json()) as HistoryVodEntry[]);
+  }, []);
+  useStoreEvents(['watchlist-updated', 'history-updated'], (name) => {
+    const refresh = name === 'watchlist-updated' ? refreshWatchlist : refreshHistoryPreview;
+    refresh().catch((error) => console.error(`Failed to refresh after ${name}`, error));
+  });
+
   useEffect(() => {
     const loadLiveStatus = async () => {
       if (subs.length === 0) {
@@ -91,7 +107,15 @@ export default function Home() {
           return;
         }
 
-        setLiveStatus((await res.json()) as LiveStatusMap);
+        const report = (await res.json()) as LiveStatusReport;
+        setLiveStatus((prev) => {
+          const next: LiveStatusMap = { ...report.live };
+          // A failed lookup says nothing about the channel; keep what we last knew.
+          for (const login of report.failed) {
+            if (prev[login]) next[login] = prev[login];
+          }
+          return next;
+        });
       } catch (error) {
         console.error('Failed to fetch live status for subs', error);
         setLiveStatus({});
@@ -702,7 +702,10 @@ function VodLivePlayer({ vodId, liveId, downloadMode }: VodLivePlayerProps) {
                       >
                         {formatClock(message.contentOffsetSeconds)}
                       </span>
-                      <span style={{ fontWeight: 800, color: 'var(--primary)' }}>
+                      <span style={{ fontWeight: 800, color: message.color || 'var(--primary)' }}>
+                        {message.isMod && <span title="Moderator">🛡️ </span>}
+                        {message.isVip && <span title="VIP">💎 </span>}
+                        {message.isSub && <span title="Subscriber">⭐ </span>}
                         {message.commenter?.displayName || 'Unknown'}:{' '}
                       </span>
                       <span style={{ color: 'var(--text)' }}>
@@ -28,8 +28,8 @@ export default function Trends() {
         if (!res.ok) throw new Error('Failed to fetch trending VODs');
         return res.json();
       })
-      .then((data: VOD[]) => {
-        const filtered = filterShortVods(data);
+      .then((data: { items: VOD[] }) => {
+        const filtered = filterShortVods(data.items);
         setAllVods(filtered);
         setVods(filtered.slice(0, PAGE_SIZE));
         setIsInitialLoading(false);
@@ -29,6 +29,10 @@ export const VODCard = React.memo<VODCardProps>(
       }
     };
 
+    const broadcastType = vod.broadcastType?.toLowerCase();
+    const broadcastLabel =
+      broadcastType === 'highlight' ? 'Temps fort' : broadcastType === 'upload' ? 'Upload' : null;
+
     const progress =
       historyEntry && historyEntry.duration > 0
         ? Math.min(100, (historyEntry.timecode / historyEntry.duration) * 100)
@@ -42,6 +46,7 @@ export const VODCard = React.memo<VODCardProps>(
             <Clock size={12} />
             {formatTime(vod.lengthSeconds)}
           </div>
+          {broadcastLabel && <div className="vod-type-badge">{broadcastLabel}</div>}
 
           <div
             className="vod-play-overlay"
@@ -5,6 +5,8 @@ type CategoryVodPage = {
   items: VOD[];
   hasMore: boolean;
   nextCursor: string | null;
+  /** Category a partial name resolved to; null when it was used as typed. */
+  category: { id: string; name: string; boxArtURL: string } | null;
 };
 
 const MIN_VOD_DURATION_SECONDS = 210;
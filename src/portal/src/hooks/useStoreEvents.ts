@@ -0,0 +1,39 @@
+import { useEffect, useRef } from 'react';
+import { safeStorageGet } from '../../../shared/utils/storage';
+
+export type StoreEventName = 'history-updated' | 'watchlist-updated';
+
+/**
+ * Calls `onEvent` whenever another client writes history or the watchlist,
+ * via the server's `/api/events` stream, so the page can refetch.
+ * EventSource can't send headers, so the token and device id go in the query;
+ * it reconnects on its own after a network drop.
+ */
+export function useStoreEvents(
+  events: StoreEventName[],
+  onEvent: (name: StoreEventName) => void
+) {
+  const onEventRef = useRef(onEvent);
+  onEventRef.current = onEvent;
+  const eventsKey = events.join(',');
+
+  useEffect(() => {
+    const params = new URLSearchParams();
+    const token =
+      safeStorageGet(sessionStorage, 'nsv_token') || safeStorageGet(localStorage, 'nsv_token');
+    const deviceId = safeStorageGet(localStorage, 'nsv_device_id');
+    if (token) params.set('t', token);
+    if (deviceId) params.set('d', deviceId);
+    const query = params.toString();
+
+    const source = new EventSource(`/api/events${query ? `?${query}` : ''}`);
+    const handler = (event: MessageEvent) => onEventRef.current(event.type as StoreEventName);
+    const names = eventsKey.split(',');
+    for (const name of names) source.addEventListener(name, handler);
+
+    return () => {
+      for (const name of names) source.removeEventListener(name, handler);
+      source.close();
+    };
+  }, [eventsKey]);
+}
@@ -94,7 +94,15 @@ export default function App() {
 
   useEffect(() => {
     invoke<ServerInfo>('get_server_info')
-      .then(setServerInfo)
+      .then(async (info) => {
+        // The QR is shown at 250 CSS px; re-render it sharp on hi-dpi screens.
+        if (window.devicePixelRatio > 1.5) {
+          const size = Math.round(250 * window.devicePixelRatio);
+          const qrcode = await invoke<string>('regenerate_qr_code', { size }).catch(() => '');
+          if (qrcode) info = { ...info, qrcode };
+        }
+        setServerInfo(info);
+      })
       .catch((err) => {
         console.error('Failed to get server info:', err);
         setServerInfo({
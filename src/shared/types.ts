@@ -5,6 +5,18 @@ export interface UserInfo {
   profileImageURL: string;
 }
 
+/** Body of every `/api` error response; branch on `code`, not `error`. */
+export interface ApiErrorBody {
+  error: string;
+  code: string;
+}
+
+export interface UserFull {
+  user: UserInfo;
+  isLive: boolean;
+  live: LiveStream | null;
+}
+
 export interface SubEntry {
   login: string;
   displayName: string;
@@ -18,6 +30,7 @@ export interface VOD {
   previewThumbnailURL: string;
   createdAt: string;
   viewCount: number;
+  broadcastType?: string | null;
   language?: string;
   game: { name: string } | null;
   owner?: {
@@ -54,9 +67,14 @@ export interface ServerInfo {
   ip: string;
   port: number;
   url: string;
+  /** Empty in localhost mode: no other device could open the URL. */
   qrcode: string;
+  bindScope?: BindScope;
 }
 
+/** `localhost` binds loopback only; applies on the next launch. */
+export type BindScope = 'lan' | 'localhost';
+
 export interface HistoryEntry {
   vodId: string;
   timecode: number;
@@ -80,6 +98,10 @@ export interface ChatMessage {
   };
   contentOffsetSeconds: number;
   createdAt: string;
+  isMod?: boolean;
+  isSub?: boolean;
+  isVip?: boolean;
+  color?: string | null;
 }
 
 export interface VideoMarker {
@@ -90,6 +112,13 @@ export interface VideoMarker {
   url?: string | null;
 }
 
+export interface Chapter {
+  positionMilliseconds: number;
+  durationMilliseconds: number;
+  description: string;
+  game: { name: string; boxArtURL: string } | null;
+}
+
 export interface WatchlistEntry {
   vodId: string;
   title: string;
@@ -111,6 +140,13 @@ export interface ExperienceSettings {
   launchAtLogin?: boolean;
   autoUpdate?: boolean;
   enabledExtensions?: string[];
+  /** Desktop QR code rendering; `size` is 200–1200 px. */
+  qr?: { size: number; errorCorrection: 'L' | 'M' | 'Q' | 'H' };
+  bindScope?: BindScope;
+  /** Hide reruns from the live feed unless a request sets `excludeReruns`. */
+  excludeReruns?: boolean;
+  /** Write-only: send `oauthToken` to set it (null/"" clears); reads only report this flag. */
+  hasOauthToken?: boolean;
 }
 
 export interface TwitchStatus {
@@ -145,6 +181,10 @@ export interface LiveStream {
   viewerCount: number;
   language?: string;
   startedAt: string;
+  /** Absent for regular streams on paths where Twitch sends no type. */
+  streamType?: 'live' | 'rerun' | 'watch_party' | 'premiere';
+  /** Streamer-set free-form tags; absent when the stream has none. */
+  tags?: string[];
   broadcaster: {
     id: string;
     login: string;
@@ -166,6 +206,48 @@ export interface LiveStreamsPage {
 
 export type LiveStatusMap = Record<string, LiveStream>;
 
+/** `/api/live/status`: logins in `failed` could not be checked, so their absence from `live` does not mean offline. */
+export interface LiveStatusReport {
+  live: LiveStatusMap;
+  checked: string[];
+  failed: string[];
+}
+
+/** `/api/live/:login/dvr-info`: how far back the live stream can be rewound. */
+export interface DvrInfo {
+  available: boolean;
+  windowSeconds: number;
+}
+
+/** `/api/selftest`: `sameNetwork` is false when the phone looks like it is on another LAN (guest Wi-Fi, VLAN), null when unknown. */
+export interface SelfTestReport {
+  serverAddresses: string[];
+  clientAddress: string | null;
+  sameNetwork: boolean | null;
+}
+
+export interface WatchTimeShare {
+  name: string;
+  seconds: number;
+}
+
+/** `/api/history/stats`: breakdowns only cover VODs Twitch still knows about. */
+export interface HistoryStats {
+  totalEntries: number;
+  totalWatchedSeconds: number;
+  completed: number;
+  byGame: WatchTimeShare[];
+  byChannel: WatchTimeShare[];
+}
+
+/** `/api/insights/top-channels`: channels by the same affinity the trends feed uses. */
+export interface ChannelAffinity {
+  login: string;
+  displayName: string;
+  profileImageURL: string;
+  score: number;
+}
+
 export type ScreenShareSourceType = 'browser' | 'application';
 
 export type ScreenShareSessionState = {